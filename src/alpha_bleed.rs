@@ -6,20 +6,28 @@ use std::collections::VecDeque;
 
 use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
 
-pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::options::BleedOptions;
+
+/// Bleeds the colors of opaque pixels into the pixels around them that aren't,
+/// according to the given options. Only colors change, never alpha.
+pub(crate) fn alpha_bleed(img: &mut DynamicImage, options: &BleedOptions) {
     let (w, h) = img.dimensions();
+    let is_opaque = |alpha: u8| alpha >= options.threshold;
 
     // Tells whether a given position has been touched by the bleeding algorithm
     // yet and is safe to sample colors from. In the first pass, we'll set all
-    // pixels that aren't totally transparent since this algorithm won't mutate
-    // them.
+    // pixels that are opaque since this algorithm won't mutate them.
     let mut can_be_sampled = Mask2::new(w, h);
 
     // The set of images that we've already visited and don't need to queue if
     // traversed again.
     let mut visited = Mask2::new(w, h);
 
-    // A queue of pixels to blend with surrounding pixels with next.
+    // A queue of pixels to blend with surrounding pixels with next, along with
+    // how many pixels away from an opaque pixel they are.
     //
     // Populated initially with all pixels that border opaque pixels. We'll use
     // it to blend outwards from each opaque pixel breadth-first.
@@ -39,38 +47,52 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
         })
     };
 
-    // Populate the set of initial positions to visit as well as positions that
-    // are valid to sample from.
-    for y in 0..h {
+    // Classify every pixel as either opaque, in which case we don't need to
+    // modify it, or not opaque but bordering such a pixel. Classifying a row
+    // only reads the image, so rows can be classified in parallel.
+    let rows: Vec<u32> = (0..h).collect();
+    let classified_rows = map_each(&rows, |&y| {
+        let mut opaque = Vec::new();
+        let mut borders_opaque = Vec::new();
+
         for x in 0..w {
-            let pixel = img.get_pixel(x, y);
-
-            if pixel[3] != 0 {
-                // This pixel is not totally transparent, so we don't need to
-                // modify it. We'll add it to the `can_be_sampled` set to
-                // indicate it's okay to sample from this pixel.
-                can_be_sampled.set(x, y);
-                visited.set(x, y);
-                continue;
+            if is_opaque(img.get_pixel(x, y)[3]) {
+                opaque.push(x);
+            } else if adjacent_positions(x, y)
+                .any(|(x_source, y_source)| is_opaque(img.get_pixel(x_source, y_source)[3]))
+            {
+                borders_opaque.push(x);
             }
+        }
 
-            // Check if any adjacent pixels have non-zero alpha.
-            let borders_opaque = adjacent_positions(x, y).any(|(x_source, y_source)| {
-                let source = img.get_pixel(x_source, y_source);
-                source[3] != 0
-            });
-
-            if borders_opaque {
-                // This pixel is totally transparent, but borders at least one
-                // opaque pixel. We'll add it to the initial set of positions to
-                // visit.
-                visited.set(x, y);
-                to_visit.push_back((x, y));
-            }
+        (opaque, borders_opaque)
+    });
+
+    // Populate the set of initial positions to visit as well as positions that
+    // are valid to sample from, in the same order as a row-by-row scan.
+    for (y, (opaque, borders_opaque)) in classified_rows.into_iter().enumerate() {
+        let y = y as u32;
+
+        for x in opaque {
+            can_be_sampled.set(x, y);
+            visited.set(x, y);
+        }
+
+        for x in borders_opaque {
+            visited.set(x, y);
+            to_visit.push_back((x, y, 1));
         }
     }
 
-    while let Some((x, y)) = to_visit.pop_front() {
+    // Pixels are bled one at a time, since each one samples from the pixels
+    // bled before it.
+    while let Some((x, y, distance)) = to_visit.pop_front() {
+        // The queue is in order of distance, so every pixel after this one is
+        // too far away as well.
+        if options.passes.is_some_and(|passes| distance > passes) {
+            break;
+        }
+
         // Compute the average color from all surrounding pixels that are
         // eligible to be sampled from.
         let mut new_color = (0, 0, 0);
@@ -86,7 +108,7 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
                 new_color.2 += source[2] as u16;
             } else if !visited.get(x_source, y_source) {
                 visited.set(x_source, y_source);
-                to_visit.push_back((x_source, y_source));
+                to_visit.push_back((x_source, y_source, distance + 1));
             }
         }
 
@@ -94,7 +116,7 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
             (new_color.0 / contributing) as u8,
             (new_color.1 / contributing) as u8,
             (new_color.2 / contributing) as u8,
-            0,
+            img.get_pixel(x, y)[3],
         ]);
 
         img.put_pixel(x, y, pixel);
@@ -105,6 +127,18 @@ pub(crate) fn alpha_bleed(img: &mut DynamicImage) {
     }
 }
 
+/// Maps each of the given items, in parallel when the `parallel` feature is
+/// enabled. Results are in the same order as the items either way.
+#[cfg(feature = "parallel")]
+fn map_each<T: Sync, U: Send>(items: &[T], f: impl Fn(&T) -> U + Sync + Send) -> Vec<U> {
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_each<T, U>(items: &[T], f: impl Fn(&T) -> U) -> Vec<U> {
+    items.iter().map(f).collect()
+}
+
 const DIRECTIONS: &[(i32, i32)] = &[
     (1, 0),
     (1, 1),
@@ -141,3 +175,218 @@ impl Mask2 {
         self.data[index as usize] = true;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::RgbaImage;
+
+    /// Makes an image with scattered opaque pixels of varying colors, with a
+    /// large transparent area that takes many rings to fill.
+    fn fixture(w: u32, h: u32) -> DynamicImage {
+        let img = RgbaImage::from_fn(w, h, |x, y| {
+            if x < w / 2 && (x * 7 + y * 13) % 29 == 0 {
+                Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        });
+
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn bleeds_into_transparent_pixels() {
+        let mut img = DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([255, 0, 0, 255]),
+            _ => Rgba([0, 0, 0, 0]),
+        }));
+
+        alpha_bleed(&mut img, &BleedOptions::default());
+
+        let img = img.as_rgba8().unwrap();
+        assert_eq!(img.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(1, 0), &Rgba([255, 0, 0, 0]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([255, 0, 0, 0]));
+    }
+
+    #[test]
+    fn threshold_decides_what_is_opaque() {
+        let original = DynamicImage::ImageRgba8(RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([255, 0, 0, 255]),
+            1 => Rgba([0, 0, 255, 128]),
+            _ => Rgba([0, 0, 0, 0]),
+        }));
+
+        // By default, the half transparent pixel is opaque enough to bleed
+        // from, and is left alone.
+        let mut img = original.clone();
+        alpha_bleed(&mut img, &BleedOptions::default());
+
+        let img = img.as_rgba8().unwrap();
+        assert_eq!(img.get_pixel(1, 0), &Rgba([0, 0, 255, 128]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([0, 0, 255, 0]));
+
+        // With a higher threshold, it's bled into instead, keeping its alpha.
+        let mut img = original;
+        let options = BleedOptions {
+            threshold: 200,
+            ..BleedOptions::default()
+        };
+        alpha_bleed(&mut img, &options);
+
+        let img = img.as_rgba8().unwrap();
+        assert_eq!(img.get_pixel(1, 0), &Rgba([255, 0, 0, 128]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([255, 0, 0, 0]));
+    }
+
+    #[test]
+    fn limited_passes() {
+        let mut img = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 1, |x, _| match x {
+            0 => Rgba([255, 0, 0, 255]),
+            _ => Rgba([0, 0, 0, 0]),
+        }));
+        let options = BleedOptions {
+            passes: Some(2),
+            ..BleedOptions::default()
+        };
+
+        alpha_bleed(&mut img, &options);
+
+        let img = img.as_rgba8().unwrap();
+        assert_eq!(img.get_pixel(1, 0), &Rgba([255, 0, 0, 0]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([255, 0, 0, 0]));
+        assert_eq!(img.get_pixel(3, 0), &Rgba([0, 0, 0, 0]));
+    }
+
+    /// The serial algorithm that alpha bleeding used before rows were
+    /// classified in parallel, kept to check that the output hasn't changed.
+    fn serial_alpha_bleed(img: &mut DynamicImage) {
+        let (w, h) = img.dimensions();
+        let mut can_be_sampled = Mask2::new(w, h);
+        let mut visited = Mask2::new(w, h);
+        let mut to_visit = VecDeque::new();
+
+        let adjacent_positions = |x, y| {
+            DIRECTIONS.iter().filter_map(move |(x_offset, y_offset)| {
+                let x_source = (x as i32) + x_offset;
+                let y_source = (y as i32) + y_offset;
+
+                if x_source < 0 || y_source < 0 || x_source >= w as i32 || y_source >= h as i32 {
+                    return None;
+                }
+
+                Some((x_source as u32, y_source as u32))
+            })
+        };
+
+        for y in 0..h {
+            for x in 0..w {
+                if img.get_pixel(x, y)[3] != 0 {
+                    can_be_sampled.set(x, y);
+                    visited.set(x, y);
+                    continue;
+                }
+
+                let borders_opaque = adjacent_positions(x, y)
+                    .any(|(x_source, y_source)| img.get_pixel(x_source, y_source)[3] != 0);
+
+                if borders_opaque {
+                    visited.set(x, y);
+                    to_visit.push_back((x, y));
+                }
+            }
+        }
+
+        while let Some((x, y)) = to_visit.pop_front() {
+            let mut new_color = (0, 0, 0);
+            let mut contributing = 0;
+
+            for (x_source, y_source) in adjacent_positions(x, y) {
+                if can_be_sampled.get(x_source, y_source) {
+                    let source = img.get_pixel(x_source, y_source);
+
+                    contributing += 1;
+                    new_color.0 += source[0] as u16;
+                    new_color.1 += source[1] as u16;
+                    new_color.2 += source[2] as u16;
+                } else if !visited.get(x_source, y_source) {
+                    visited.set(x_source, y_source);
+                    to_visit.push_back((x_source, y_source));
+                }
+            }
+
+            let pixel = Rgba([
+                (new_color.0 / contributing) as u8,
+                (new_color.1 / contributing) as u8,
+                (new_color.2 / contributing) as u8,
+                0,
+            ]);
+
+            img.put_pixel(x, y, pixel);
+            can_be_sampled.set(x, y);
+        }
+    }
+
+    /// Checked with and without the `parallel` feature, so that both give the
+    /// same bytes as the serial algorithm.
+    #[test]
+    fn matches_serial_version() {
+        for &(w, h) in &[(64, 64), (37, 101), (1, 1), (200, 3)] {
+            let mut expected = fixture(w, h);
+            serial_alpha_bleed(&mut expected);
+
+            let mut img = fixture(w, h);
+            alpha_bleed(&mut img, &BleedOptions::default());
+
+            assert!(
+                img.as_bytes() == expected.as_bytes(),
+                "fixture {}x{} differs from the serial version",
+                w,
+                h
+            );
+        }
+    }
+
+    /// Times bleeding a large image on one thread and on every thread rayon
+    /// has. Run it with
+    /// `cargo test --release bleed_benchmark -- --ignored --nocapture`.
+    #[cfg(feature = "parallel")]
+    #[test]
+    #[ignore]
+    fn bleed_benchmark() {
+        use std::time::{Duration, Instant};
+
+        const RUNS: u32 = 5;
+
+        let original = fixture(1024, 1024);
+
+        let time_on = |threads: usize| -> Duration {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+
+            let started = Instant::now();
+            for _ in 0..RUNS {
+                let mut img = original.clone();
+                pool.install(|| alpha_bleed(&mut img, &BleedOptions::default()));
+            }
+
+            started.elapsed() / RUNS
+        };
+
+        let threads = rayon::current_num_threads();
+        let serial = time_on(1);
+        let parallel = time_on(threads);
+
+        println!(
+            "1024x1024: {:?} on 1 thread, {:?} on {} threads ({:.2}x)",
+            serial,
+            parallel,
+            threads,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
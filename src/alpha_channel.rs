@@ -0,0 +1,84 @@
+//! Transforms that change how an image's transparency is stored, for Roblox
+//! surfaces that don't expect the straight alpha that images usually have.
+
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba};
+
+/// Composites an image over a solid background color, producing an image
+/// without an alpha channel.
+pub(crate) fn flatten_alpha(img: &DynamicImage, background: Rgb<u8>) -> DynamicImage {
+    let (w, h) = img.dimensions();
+
+    let flattened = RgbImage::from_fn(w, h, |x, y| {
+        let Rgba([r, g, b, a]) = img.get_pixel(x, y);
+
+        Rgb([
+            blend(r, background[0], a),
+            blend(g, background[1], a),
+            blend(b, background[2], a),
+        ])
+    });
+
+    DynamicImage::ImageRgb8(flattened)
+}
+
+/// Multiplies the color of every pixel by its alpha, so that the image has
+/// premultiplied alpha instead of straight alpha.
+pub(crate) fn premultiply_alpha(img: &mut DynamicImage) {
+    let mut rgba = img.to_rgba8();
+
+    for pixel in rgba.pixels_mut() {
+        let Rgba([r, g, b, a]) = *pixel;
+        *pixel = Rgba([scale(r, a), scale(g, a), scale(b, a), a]);
+    }
+
+    *img = DynamicImage::ImageRgba8(rgba);
+}
+
+/// Blends a color channel over a background channel with the given alpha.
+fn blend(value: u8, background: u8, alpha: u8) -> u8 {
+    scale(value, alpha) + scale(background, 255 - alpha)
+}
+
+/// Scales a color channel by an alpha value, rounding to the nearest value.
+fn scale(value: u8, alpha: u8) -> u8 {
+    ((value as u16 * alpha as u16 + 127) / 255) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::RgbaImage;
+
+    fn image_of(pixels: &[[u8; 4]]) -> DynamicImage {
+        let mut img = RgbaImage::new(pixels.len() as u32, 1);
+
+        for (x, pixel) in pixels.iter().enumerate() {
+            img.put_pixel(x as u32, 0, Rgba(*pixel));
+        }
+
+        DynamicImage::ImageRgba8(img)
+    }
+
+    #[test]
+    fn flatten_over_background() {
+        let img = image_of(&[[255, 0, 0, 255], [255, 0, 0, 0], [255, 0, 0, 128]]);
+        let flattened = flatten_alpha(&img, Rgb([0, 0, 255]));
+
+        let flattened = flattened.as_rgb8().unwrap();
+        assert_eq!(flattened.get_pixel(0, 0), &Rgb([255, 0, 0]));
+        assert_eq!(flattened.get_pixel(1, 0), &Rgb([0, 0, 255]));
+        assert_eq!(flattened.get_pixel(2, 0), &Rgb([128, 0, 127]));
+    }
+
+    #[test]
+    fn premultiply() {
+        let mut img = image_of(&[[255, 100, 0, 255], [255, 100, 0, 0], [255, 100, 0, 128]]);
+        premultiply_alpha(&mut img);
+
+        let img = img.as_rgba8().unwrap();
+        assert_eq!(img.get_pixel(0, 0), &Rgba([255, 100, 0, 255]));
+        assert_eq!(img.get_pixel(1, 0), &Rgba([0, 0, 0, 0]));
+        assert_eq!(img.get_pixel(2, 0), &Rgba([128, 50, 0, 128]));
+    }
+}
@@ -1,8 +1,10 @@
 //! Implementation of automatically fetching authentication cookie from a Roblox
 //! Studio installation.
 
+use crate::secret::Secret;
+
 #[cfg(windows)]
-pub fn get_auth_cookie() -> Option<String> {
+pub fn get_auth_cookie() -> Option<Secret> {
     use winreg::{enums::HKEY_CURRENT_USER, RegKey};
 
     let hkcu = RegKey::predef(HKEY_CURRENT_USER);
@@ -11,6 +13,56 @@ pub fn get_auth_cookie() -> Option<String> {
         .ok()?;
 
     let entry: String = cookies.get_value(".ROBLOSECURITY").ok()?;
+
+    parse_cookie_entry(&entry).map(Secret::new)
+}
+
+/// On macOS, Roblox Studio keeps its cookies in the preferences of
+/// `com.roblox.RobloxStudioBrowser`, which `defaults` can read for us.
+#[cfg(target_os = "macos")]
+pub fn get_auth_cookie() -> Option<Secret> {
+    use std::process::Command;
+
+    let output = Command::new("defaults")
+        .args(&["read", "com.roblox.RobloxStudioBrowser"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        log::debug!("Couldn't read Roblox Studio's preferences with `defaults`");
+        return None;
+    }
+
+    let entry = find_defaults_entry(&String::from_utf8_lossy(&output.stdout))?;
+
+    parse_cookie_entry(&entry).map(Secret::new)
+}
+
+/// Roblox Studio doesn't run natively on Linux, but it can run under Wine, in
+/// which case its cookie is in the registry of the Wine prefix.
+#[cfg(target_os = "linux")]
+pub fn get_auth_cookie() -> Option<Secret> {
+    use std::{env, fs, path::PathBuf};
+
+    let prefix = match env::var_os("WINEPREFIX") {
+        Some(prefix) => PathBuf::from(prefix),
+        None => PathBuf::from(env::var_os("HOME")?).join(".wine"),
+    };
+
+    let registry = fs::read_to_string(prefix.join("user.reg")).ok()?;
+    let entry = find_wine_registry_entry(&registry)?;
+
+    parse_cookie_entry(&entry).map(Secret::new)
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+pub fn get_auth_cookie() -> Option<Secret> {
+    None
+}
+
+/// Roblox Studio stores its cookie as comma-separated `KEY::<value>` pairs,
+/// where `COOK` holds the value of the cookie itself.
+fn parse_cookie_entry(entry: &str) -> Option<String> {
     let mut cookie = None;
 
     for kv_pair in entry.split(',') {
@@ -39,7 +91,88 @@ pub fn get_auth_cookie() -> Option<String> {
     cookie.map(Into::into)
 }
 
-#[cfg(not(windows))]
-pub fn get_auth_cookie() -> Option<String> {
+/// Finds the `.ROBLOSECURITY` entry in the output of `defaults read`, which
+/// looks like `"roblox.com.ROBLOSECURITY" = "SEC::<YES>,COOK::<...>";`.
+#[cfg(any(target_os = "macos", test))]
+fn find_defaults_entry(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once('=')?;
+
+        if !key.trim().trim_matches('"').ends_with(".ROBLOSECURITY") {
+            return None;
+        }
+
+        let value = value.trim().trim_end_matches(';');
+        Some(value.trim_matches('"').to_owned())
+    })
+}
+
+/// Finds the `.ROBLOSECURITY` value of Roblox Studio's key in a Wine
+/// `user.reg` file, where keys are written like `[Software\\Roblox\\...]`
+/// and followed by their values, one per line.
+#[cfg(any(target_os = "linux", test))]
+fn find_wine_registry_entry(registry: &str) -> Option<String> {
+    const KEY: &str = r"[Software\\Roblox\\RobloxStudioBrowser\\roblox.com]";
+
+    let mut in_key = false;
+
+    for line in registry.lines() {
+        if line.starts_with('[') {
+            in_key = line.starts_with(KEY);
+            continue;
+        }
+
+        if in_key {
+            if let Some(value) = line.strip_prefix("\".ROBLOSECURITY\"=") {
+                return Some(value.trim().trim_matches('"').to_owned());
+            }
+        }
+    }
+
     None
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cookie_entry() {
+        let entry = "SEC::<YES>,EXP::<2050-01-01T00:00:00Z>,COOK::<_|WARNING:abc>";
+        assert_eq!(parse_cookie_entry(entry), Some("_|WARNING:abc".to_owned()));
+
+        assert_eq!(parse_cookie_entry("SEC::<YES>"), None);
+        assert_eq!(parse_cookie_entry("COOK::abc"), None);
+    }
+
+    #[test]
+    fn macos_defaults_entry() {
+        let output = r#"{
+    "roblox.com.RBXEventTrackerV2" = "CreateDate=1";
+    "roblox.com.ROBLOSECURITY" = "SEC::<YES>,COOK::<_|WARNING:abc>";
+}"#;
+
+        assert_eq!(
+            find_defaults_entry(output),
+            Some("SEC::<YES>,COOK::<_|WARNING:abc>".to_owned())
+        );
+    }
+
+    #[test]
+    fn wine_registry_entry() {
+        let registry = r#"WINE REGISTRY Version 2
+
+[Software\\Other\\roblox.com] 1600000000
+".ROBLOSECURITY"="COOK::<wrong>"
+
+[Software\\Roblox\\RobloxStudioBrowser\\roblox.com] 1600000000
+#time=1d6b7c0e1c1e2a0
+".ROBLOSECURITY"="SEC::<YES>,COOK::<_|WARNING:abc>"
+"#;
+
+        assert_eq!(
+            find_wine_registry_entry(registry),
+            Some("SEC::<YES>,COOK::<_|WARNING:abc>".to_owned())
+        );
+    }
+}
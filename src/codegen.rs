@@ -27,6 +27,20 @@ pub fn perform_codegen(output_path: Option<&Path>, inputs: &[&SyncInput]) -> io:
     }
 }
 
+/// Generates the source of a module returning a flat table from the given
+/// names to the asset URLs of the assets they refer to.
+pub fn codegen_asset_map(assets: &BTreeMap<String, AssetId>) -> String {
+    let mut table = Table::new();
+
+    for (name, id) in assets {
+        table.add_entry(name, codegen_just_asset_url(id));
+    }
+
+    let ast = Statement::Return(Expression::Table(table));
+
+    format!("{}\n{}", CODEGEN_HEADER, ast)
+}
+
 /// Tree used to track and group inputs hierarchically, before turning them into
 /// Lua tables.
 enum GroupedItem<'a> {
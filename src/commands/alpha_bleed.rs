@@ -0,0 +1,155 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::{
+        bleed_and_encode_png, decode_image, expand_image_glob, find_images, UploadImageError,
+    },
+    glob::{self, Glob},
+    options::{AlphaBleedOptions, BleedOptions, GlobalOptions},
+};
+
+pub fn alpha_bleed(
+    global: GlobalOptions,
+    options: AlphaBleedOptions,
+) -> Result<(), AlphaBleedError> {
+    let pattern = options.input.to_string_lossy();
+
+    let (root, paths) = if glob::is_pattern(&pattern) {
+        let paths = expand_image_glob(&pattern)?;
+        let glob = Glob::new(&pattern).map_err(|source| UploadImageError::InvalidGlob {
+            pattern: pattern.clone().into_owned(),
+            source,
+        })?;
+
+        (glob.get_prefix(), paths)
+    } else if options.input.is_dir() {
+        (options.input.clone(), find_images(&options.input)?)
+    } else {
+        bleed_file(&options.input, &options.output, &options.bleed)?;
+
+        if !global.quiet {
+            eprintln!("Wrote {}", options.output.display());
+        }
+
+        return Ok(());
+    };
+
+    for path in &paths {
+        let output = mirrored_path(&root, path, &options.output);
+        bleed_file(path, &output, &options.bleed)?;
+
+        log::info!("Wrote {}", output.display());
+    }
+
+    if !global.quiet {
+        eprintln!(
+            "Wrote {} image(s) to {}",
+            paths.len(),
+            options.output.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn bleed_file(input: &Path, output: &Path, bleed: &BleedOptions) -> Result<(), AlphaBleedError> {
+    let data = fs::read(input)?;
+    let mut img = decode_image(input, &data, None)?;
+
+    let encoded = bleed_and_encode_png(&mut img, bleed)
+        .map_err(|source| UploadImageError::Encode { source })?;
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(output, encoded)?;
+
+    Ok(())
+}
+
+/// Images from a directory are written to the same place relative to the
+/// output directory, as PNGs.
+fn mirrored_path(root: &Path, path: &Path, output: &Path) -> PathBuf {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+
+    output.join(relative).with_extension("png")
+}
+
+#[derive(Debug, Error)]
+pub enum AlphaBleedError {
+    #[error(transparent)]
+    Image {
+        #[from]
+        source: UploadImageError,
+    },
+
+    #[error(transparent)]
+    WalkDir {
+        #[from]
+        source: walkdir::Error,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{GenericImageView, Rgba, RgbaImage};
+    use structopt::StructOpt;
+
+    use crate::options::Options;
+
+    #[test]
+    fn mirror_input_layout() {
+        assert_eq!(
+            mirrored_path(
+                Path::new("assets"),
+                Path::new("assets/icons/save.jpg"),
+                Path::new("bled")
+            ),
+            Path::new("bled/icons/save.png")
+        );
+    }
+
+    #[test]
+    fn bleed_directory() {
+        let root = std::env::temp_dir().join("tarmac-bleed-directory");
+        let input = root.join("input");
+        let output = root.join("output");
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(input.join("icons")).unwrap();
+
+        let mut img = RgbaImage::new(2, 1);
+        img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        img.save(input.join("icons/red.png")).unwrap();
+
+        let global = Options::from_iter(&["tarmac", "--quiet", "info", "1"]).global;
+        let options = AlphaBleedOptions {
+            input: input.clone(),
+            output: output.clone(),
+            bleed: BleedOptions::default(),
+        };
+
+        alpha_bleed(global, options).unwrap();
+
+        let bled = image::open(output.join("icons/red.png")).unwrap();
+        assert_eq!(bled.dimensions(), (2, 1));
+        assert_eq!(bled.get_pixel(1, 0), Rgba([255, 0, 0, 0]));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
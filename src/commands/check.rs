@@ -0,0 +1,206 @@
+use fs_err as fs;
+use serde::Serialize;
+use thiserror::Error;
+
+use std::path::Path;
+
+use image::GenericImageView;
+
+use crate::{
+    commands::{
+        decode_image, error_chain, expand_image_glob, find_images, is_image_path, prepare_image,
+        print_json, UploadImageError,
+    },
+    glob,
+    options::{CheckOptions, GlobalOptions, ImageUploadSettings, OutputFormat},
+};
+
+pub fn check(global: GlobalOptions, options: CheckOptions) -> Result<(), CheckError> {
+    let mut paths = Vec::new();
+
+    for path in &options.paths {
+        let pattern = path.to_string_lossy();
+
+        if glob::is_pattern(&pattern) {
+            paths.extend(expand_image_glob(&pattern)?);
+        } else if path.is_dir() {
+            paths.extend(find_images(path)?);
+        } else {
+            paths.push(path.clone());
+        }
+    }
+
+    let mut records = Vec::new();
+    let mut error_count = 0;
+
+    for path in &paths {
+        let result = check_image_file(path, &options.settings);
+
+        if result.is_err() {
+            error_count += 1;
+        }
+
+        match global.output_format {
+            OutputFormat::Text => match &result {
+                Ok(checked) => println!("{}\t{}", path.display(), checked),
+                Err(error) => println!("{}\t{}", path.display(), error_chain(error)),
+            },
+            OutputFormat::Json => records.push(CheckRecord::new(path, &result)),
+        }
+    }
+
+    if global.output_format == OutputFormat::Json {
+        print_json(&records);
+    }
+
+    if !global.quiet {
+        eprintln!("Checked {} image(s), {} failed.", paths.len(), error_count);
+    }
+
+    if error_count == 0 {
+        Ok(())
+    } else {
+        Err(CheckError::HadErrors { error_count })
+    }
+}
+
+/// Decodes, checks, and encodes an image the way `upload_image_file` would,
+/// without uploading it.
+fn check_image_file(
+    path: &Path,
+    settings: &ImageUploadSettings,
+) -> Result<CheckedImage, UploadImageError> {
+    if !is_image_path(path) {
+        return Err(UploadImageError::UnsupportedFileType {
+            path: path.to_owned(),
+        });
+    }
+
+    let data = fs::read(path)?;
+    let img = decode_image(path, &data, settings.svg_size)?;
+    let (width, height) = img.dimensions();
+
+    let encoded = prepare_image(img, settings)?;
+
+    Ok(CheckedImage {
+        width,
+        height,
+        encoded_size: encoded.len(),
+    })
+}
+
+/// An image that passed its check, and the size of the PNG that would be
+/// uploaded for it.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckedImage {
+    width: u32,
+    height: u32,
+    encoded_size: usize,
+}
+
+impl std::fmt::Display for CheckedImage {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "ok ({}x{}, {} bytes)",
+            self.width, self.height, self.encoded_size
+        )
+    }
+}
+
+/// What `--output-format json` prints for each checked image.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckRecord {
+    path: String,
+
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    image: Option<CheckedImage>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl CheckRecord {
+    fn new(path: &Path, result: &Result<CheckedImage, UploadImageError>) -> Self {
+        Self {
+            path: path.display().to_string(),
+            image: result.as_ref().ok().copied(),
+            error: result.as_ref().err().map(|error| error_chain(error)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CheckError {
+    #[error("{error_count} image(s) failed their check")]
+    HadErrors { error_count: usize },
+
+    #[error(transparent)]
+    Image {
+        #[from]
+        source: UploadImageError,
+    },
+
+    #[error(transparent)]
+    WalkDir {
+        #[from]
+        source: walkdir::Error,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::{DynamicImage, RgbaImage};
+    use structopt::StructOpt;
+
+    use crate::options::{Options, Subcommand};
+
+    fn settings() -> ImageUploadSettings {
+        match Options::from_iter(&["tarmac", "check", "unused.png"]).command {
+            Subcommand::Check(options) => options.settings,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn check_sizes() {
+        let path = std::env::temp_dir().join("tarmac-check_sizes.png");
+        DynamicImage::ImageRgba8(RgbaImage::new(20, 10))
+            .save(&path)
+            .unwrap();
+
+        let checked = check_image_file(&path, &settings()).unwrap();
+        assert_eq!((checked.width, checked.height), (20, 10));
+
+        let small = ImageUploadSettings {
+            max_size: 16,
+            ..settings()
+        };
+        assert!(matches!(
+            check_image_file(&path, &small),
+            Err(UploadImageError::TooLarge { .. })
+        ));
+
+        let resized = ImageUploadSettings {
+            resize: true,
+            ..small
+        };
+        assert!(check_image_file(&path, &resized).is_ok());
+    }
+
+    #[test]
+    fn check_corrupt_file() {
+        let path = std::env::temp_dir().join("tarmac-check_corrupt_file.png");
+        fs::write(&path, b"not a png").unwrap();
+
+        assert!(check_image_file(&path, &settings()).is_err());
+        assert!(matches!(
+            check_image_file(Path::new("notes.txt"), &settings()),
+            Err(UploadImageError::UnsupportedFileType { .. })
+        ));
+    }
+}
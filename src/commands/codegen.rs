@@ -0,0 +1,168 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::{collections::BTreeMap, io, path::Path};
+
+use crate::{
+    codegen::codegen_asset_map,
+    data::{AssetId, UploadLock, UploadLockError},
+    diff::unified_diff,
+    options::{CodegenOptions, GlobalOptions, KeyStyle},
+};
+
+pub fn codegen(global: GlobalOptions, options: CodegenOptions) -> Result<(), CodegenError> {
+    let lock = UploadLock::read_from_folder(".")?;
+    let assets = asset_map(&lock, options.key_style)?;
+    let generated = codegen_asset_map(&assets);
+
+    if global.dry_run {
+        print_changes(&global, &options.output, &generated)?;
+        return Ok(());
+    }
+
+    fs::write(&options.output, generated)?;
+
+    log::info!(
+        "Generated {} with {} asset(s)",
+        options.output.display(),
+        assets.len()
+    );
+
+    Ok(())
+}
+
+/// Prints a diff between the module at `output` and the module that would be
+/// generated, without writing anything. A module that doesn't exist yet is
+/// compared as if it were empty.
+fn print_changes(global: &GlobalOptions, output: &Path, generated: &str) -> io::Result<()> {
+    let existing = match fs::read_to_string(output) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let name = output.display().to_string();
+
+    match unified_diff(&existing, generated, &name, &name) {
+        Some(diff) => print!("{}", diff),
+        None => {
+            if !global.quiet {
+                eprintln!("{} is already up to date.", name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Names each upload recorded in the lock according to the key style.
+fn asset_map(
+    lock: &UploadLock,
+    key_style: KeyStyle,
+) -> Result<BTreeMap<String, AssetId>, CodegenError> {
+    let mut assets = BTreeMap::new();
+
+    for (path, upload) in &lock.uploads {
+        let key = format!(
+            "{}{}",
+            upload.name_prefix,
+            asset_key(Path::new(path), key_style)
+        );
+
+        if assets.contains_key(&key) {
+            return Err(CodegenError::DuplicateKey { key });
+        }
+
+        assets.insert(key, AssetId::Id(upload.id));
+    }
+
+    Ok(assets)
+}
+
+fn asset_key(path: &Path, key_style: KeyStyle) -> String {
+    match key_style {
+        KeyStyle::Path => path
+            .with_extension("")
+            .to_string_lossy()
+            .trim_start_matches("./")
+            .to_owned(),
+        KeyStyle::Stem => path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    #[error(
+        "More than one uploaded image would be named {key:?}. \
+         Try --key-style path to name images by their full path."
+    )]
+    DuplicateKey { key: String },
+
+    #[error(transparent)]
+    UploadLock {
+        #[from]
+        source: UploadLockError,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::options::ImageAssetType;
+
+    #[test]
+    fn key_styles() {
+        let path = Path::new("icons/save.png");
+
+        assert_eq!(asset_key(path, KeyStyle::Path), "icons/save");
+        assert_eq!(asset_key(path, KeyStyle::Stem), "save");
+    }
+
+    #[test]
+    fn prefixed_keys() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("buttons/save.png"),
+            "abc".to_owned(),
+            1,
+            ImageAssetType::Decal,
+        );
+        lock.set_name_prefix(Path::new("buttons/save.png"), "ui/");
+
+        let assets = asset_map(&lock, KeyStyle::Stem).unwrap();
+        assert!(assets.contains_key("ui/save"));
+    }
+
+    #[test]
+    fn duplicate_stems() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("a/icon.png"),
+            "abc".to_owned(),
+            1,
+            ImageAssetType::Decal,
+        );
+        lock.insert(
+            Path::new("b/icon.png"),
+            "def".to_owned(),
+            2,
+            ImageAssetType::Decal,
+        );
+
+        assert!(asset_map(&lock, KeyStyle::Path).is_ok());
+        assert!(matches!(
+            asset_map(&lock, KeyStyle::Stem),
+            Err(CodegenError::DuplicateKey { .. })
+        ));
+    }
+}
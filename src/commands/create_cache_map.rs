@@ -13,7 +13,7 @@ pub fn create_cache_map(
     global: GlobalOptions,
     options: CreateCacheMapOptions,
 ) -> anyhow::Result<()> {
-    let mut api_client = RobloxApiClient::new(global.auth);
+    let mut api_client = RobloxApiClient::from_options(&global)?;
 
     let project_path = match options.project_path {
         Some(path) => path,
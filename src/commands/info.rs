@@ -0,0 +1,62 @@
+use thiserror::Error;
+
+use crate::{
+    commands::print_json,
+    options::{GlobalOptions, InfoOptions, OutputFormat},
+    roblox_web_api::{AssetInfo, RobloxApiClient, RobloxApiError},
+};
+
+pub fn info(global: GlobalOptions, options: InfoOptions) -> Result<(), InfoError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    let info = client.get_asset_info(options.asset_id)?;
+
+    match global.output_format {
+        OutputFormat::Text => print_info(&info),
+        OutputFormat::Json => print_json(&info),
+    }
+
+    Ok(())
+}
+
+fn print_info(info: &AssetInfo) {
+    println!("Asset ID: {}", info.asset_id);
+    println!("Name: {}", info.name);
+
+    if let Some(description) = &info.description {
+        println!("Description: {}", description);
+    }
+
+    match asset_type_name(info.asset_type_id) {
+        Some(name) => println!("Type: {} ({})", name, info.asset_type_id),
+        None => println!("Type: {}", info.asset_type_id),
+    }
+
+    println!(
+        "Creator: {} ({} {})",
+        info.creator.name, info.creator.creator_type, info.creator.id
+    );
+    println!("Created: {}", info.created);
+    println!("Updated: {}", info.updated);
+}
+
+/// Names the asset types that Tarmac deals with. Roblox has many more.
+fn asset_type_name(asset_type_id: u32) -> Option<&'static str> {
+    match asset_type_id {
+        1 => Some("Image"),
+        3 => Some("Audio"),
+        10 => Some("Model"),
+        13 => Some("Decal"),
+        40 => Some("MeshPart"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum InfoError {
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
@@ -0,0 +1,128 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::{io, path::PathBuf};
+
+use crate::{
+    data::CONFIG_FILENAME,
+    options::{GlobalOptions, InitOptions},
+};
+
+pub fn init(global: GlobalOptions, options: InitOptions) -> Result<(), InitError> {
+    let path = options.path.join(CONFIG_FILENAME);
+
+    if path.exists() && !options.force {
+        return Err(InitError::AlreadyExists { path });
+    }
+
+    fs::create_dir_all(&options.path)?;
+
+    let name = match options.name {
+        Some(name) => name,
+        None => fs::canonicalize(&options.path)?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("tarmac-project")),
+    };
+
+    fs::write(&path, config_template(&name, options.group_id))?;
+
+    if !global.quiet {
+        eprintln!("Created {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Writes a tarmac.toml for a new project. Settings that aren't given are
+/// left commented out, set to examples of the values they take, so that
+/// people can see what's available without reading the docs first.
+fn config_template(name: &str, group_id: Option<u64>) -> String {
+    let group_id = match group_id {
+        Some(id) => format!("upload-to-group-id = {}", id),
+        None => String::from("# upload-to-group-id = 1234567"),
+    };
+
+    format!(
+        r#"# The name of the project, used in logging and error reporting.
+name = {name}
+
+# Upload every asset to this group instead of the authenticated user. Syncing
+# fails if the user can't create assets in the group.
+{group_id}
+
+# Write the URL of every asset the project uses to this file, one per line.
+# asset-list-path = "asset-list.txt"
+
+# Each input tells Tarmac where to find assets, and whether to generate a Lua
+# module that refers to them. Add one of these for each folder of assets.
+# [[inputs]]
+# glob = "assets/**/*.png"
+# codegen = true
+# codegen-path = "src/assets.lua"
+# codegen-base-path = "assets"
+
+# Defaults for Tarmac's global command line options. Options given on the
+# command line take precedence over these.
+[options]
+# api-base = "roblox.com"
+# retry-policy = "exponential:3:250"
+# max-retry-after = 60
+# timeout = 60
+# connect-timeout = 10
+"#,
+        name = toml::Value::String(name.to_owned()),
+        group_id = group_id,
+    )
+}
+
+#[derive(Debug, Error)]
+pub enum InitError {
+    #[error("{} already exists. Pass --force to replace it.", .path.display())]
+    AlreadyExists { path: PathBuf },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::data::Config;
+
+    #[test]
+    fn template_is_valid_config() {
+        let config: Config = toml::from_str(&config_template("my \"game\"", Some(42))).unwrap();
+
+        assert_eq!(config.name, "my \"game\"");
+        assert_eq!(config.upload_to_group_id, Some(42));
+        assert!(config.inputs.is_empty());
+
+        let config: Config = toml::from_str(&config_template("game", None)).unwrap();
+        assert_eq!(config.upload_to_group_id, None);
+    }
+
+    #[test]
+    fn examples_are_valid_config() {
+        // Uncommenting every example setting should give a config that works.
+        let uncommented: String = config_template("game", None)
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(setting) if setting.contains(" = ") || setting.starts_with('[') => setting,
+                _ => line,
+            })
+            .map(|line| format!("{}\n", line))
+            .collect();
+
+        let config: Config = toml::from_str(&uncommented).unwrap();
+
+        assert_eq!(config.upload_to_group_id, Some(1234567));
+        assert_eq!(config.inputs.len(), 1);
+        assert_eq!(config.options.api_base.as_deref(), Some("roblox.com"));
+        assert_eq!(config.options.connect_timeout, Some(10));
+    }
+}
@@ -0,0 +1,63 @@
+use thiserror::Error;
+
+use crate::{
+    commands::print_json,
+    data::{UploadLock, UploadLockError},
+    options::{GlobalOptions, ListOptions, OutputFormat},
+    roblox_web_api::{InventoryAsset, RobloxApiClient, RobloxApiError},
+};
+
+pub fn list(global: GlobalOptions, options: ListOptions) -> Result<(), ListError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    if options.user_id.is_none() {
+        client.require_auth()?;
+    }
+
+    let assets = match &options.tag {
+        Some(tag) => {
+            // Which assets have the tag is only known locally, so every asset
+            // has to be fetched before the limit can be applied.
+            let tagged = UploadLock::read_from_folder(".")?.ids_tagged(tag);
+            let mut assets = client.list_assets(options.user_id, None)?;
+
+            assets.retain(|asset| tagged.contains(&asset.asset_id));
+            assets.truncate(options.limit.unwrap_or(usize::MAX));
+            assets
+        }
+        None => client.list_assets(options.user_id, options.limit)?,
+    };
+
+    match global.output_format {
+        OutputFormat::Text => print_assets(&assets),
+        OutputFormat::Json => print_json(&assets),
+    }
+
+    Ok(())
+}
+
+fn print_assets(assets: &[InventoryAsset]) {
+    if assets.is_empty() {
+        println!("No assets were found.");
+        return;
+    }
+
+    for asset in assets {
+        println!("{}\t{}\t{}", asset.asset_id, asset.asset_type, asset.name);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ListError {
+    #[error(transparent)]
+    UploadLock {
+        #[from]
+        source: UploadLockError,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
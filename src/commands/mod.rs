@@ -1,9 +1,37 @@
+mod alpha_bleed;
 mod asset_list;
+mod check;
+mod codegen;
 mod create_cache_map;
+mod info;
+mod init;
+mod list;
+mod ping;
+mod spritesheet;
 mod sync;
+mod thumbnail;
+mod upload_audio;
+mod upload_dir;
 mod upload_image;
+mod upload_list;
+mod upload_record;
+mod whoami;
 
+pub use alpha_bleed::*;
 pub use asset_list::*;
+pub use check::*;
+pub use codegen::*;
 pub use create_cache_map::*;
+pub use info::*;
+pub use init::*;
+pub use list::*;
+pub use ping::*;
+pub use spritesheet::*;
 pub use sync::*;
+pub use thumbnail::*;
+pub use upload_audio::*;
+pub use upload_dir::*;
 pub use upload_image::*;
+pub use upload_list::*;
+pub use upload_record::*;
+pub use whoami::*;
@@ -0,0 +1,65 @@
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    commands::print_json,
+    options::{GlobalOptions, OutputFormat},
+    roblox_web_api::{PingResponse, RobloxApiClient, RobloxApiError},
+};
+
+pub fn ping(global: GlobalOptions) -> Result<(), PingError> {
+    let client = RobloxApiClient::without_auth(&global)?;
+    let response = client.ping()?;
+
+    match global.output_format {
+        OutputFormat::Text => println!(
+            "{} responded with {} in {}ms",
+            response.url,
+            response.status,
+            response.latency.as_millis()
+        ),
+        OutputFormat::Json => print_json(&PingRecord::from(&response)),
+    }
+
+    if response.is_healthy() {
+        Ok(())
+    } else {
+        Err(PingError::Unhealthy {
+            status: response.status,
+        })
+    }
+}
+
+/// How Roblox responded to the ping, as printed with `--output-format json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PingRecord<'a> {
+    url: &'a str,
+    status: u16,
+    latency_ms: u128,
+    healthy: bool,
+}
+
+impl<'a> From<&'a PingResponse> for PingRecord<'a> {
+    fn from(response: &'a PingResponse) -> Self {
+        Self {
+            url: &response.url,
+            status: response.status.as_u16(),
+            latency_ms: response.latency.as_millis(),
+            healthy: response.is_healthy(),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PingError {
+    #[error("Roblox was reached, but responded with a server error ({status})")]
+    Unhealthy { status: StatusCode },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
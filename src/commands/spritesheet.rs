@@ -0,0 +1,176 @@
+use fs_err as fs;
+use serde::Serialize;
+use thiserror::Error;
+
+use std::{borrow::Cow, collections::BTreeMap, io, path::Path};
+
+use crate::{
+    commands::{bleed_and_encode_png, decode_image, find_images, UploadImageError},
+    options::{BleedOptions, GlobalOptions, ImageAssetType, SpritesheetOptions},
+    roblox_web_api::{ImageUploadData, RobloxApiClient, RobloxApiError},
+    spritesheet::{pack_spritesheets, PackError},
+};
+
+pub fn spritesheet(
+    global: GlobalOptions,
+    options: SpritesheetOptions,
+) -> Result<(), SpritesheetError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    client.require_auth()?;
+
+    let mut sprites = BTreeMap::new();
+
+    for path in find_images(&options.path)? {
+        let data = fs::read(&path)?;
+        let image = decode_image(&path, &data, None)?;
+
+        sprites.insert(sprite_name(&options.path, &path), image);
+    }
+
+    let max_size = (options.max_size, options.max_size);
+    let sheets = pack_spritesheets(&sprites, max_size)?;
+
+    log::info!(
+        "Packed {} sprite(s) into {} spritesheet(s)",
+        sprites.len(),
+        sheets.len()
+    );
+
+    let mut manifest = SpritesheetManifest::default();
+
+    for (index, mut sheet) in sheets.into_iter().enumerate() {
+        let encoded = bleed_and_encode_png(&mut sheet.image, &BleedOptions::default())
+            .map_err(|source| UploadImageError::Encode { source })?;
+        let name = format!("{}-{}", options.name, index);
+
+        let asset_id = if global.dry_run {
+            log::info!("Dry run: not uploading {}", name);
+            None
+        } else {
+            let response = client.upload_image(ImageUploadData {
+                image_data: Cow::Owned(encoded),
+                name: &name,
+                description: &options.description,
+                group_id: None,
+                asset_type: ImageAssetType::default(),
+            })?;
+
+            log::info!("Uploaded {} as {}", name, response.backing_asset_id);
+            Some(response.backing_asset_id)
+        };
+
+        for (sprite, slice) in sheet.slices {
+            manifest.sprites.insert(
+                sprite,
+                SpriteEntry {
+                    sheet: index,
+                    asset_id,
+                    offset: slice.min(),
+                    size: slice.size(),
+                },
+            );
+        }
+
+        manifest.sheets.push(SheetEntry { name, asset_id });
+    }
+
+    let serialized = serde_json::to_string_pretty(&manifest)?;
+    fs::write(&options.output, serialized)?;
+
+    if !global.quiet {
+        eprintln!("Wrote spritesheet manifest to {}", options.output.display());
+    }
+
+    Ok(())
+}
+
+/// Sprites are named by their path relative to the directory they came from,
+/// without an extension, like `icons/save`.
+fn sprite_name(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+
+    relative
+        .with_extension("")
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Describes the spritesheets that were uploaded, and where each sprite can
+/// be found in them.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpritesheetManifest {
+    sheets: Vec<SheetEntry>,
+    sprites: BTreeMap<String, SpriteEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SheetEntry {
+    name: String,
+    asset_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SpriteEntry {
+    /// The index of the sheet in `SpritesheetManifest::sheets` that contains
+    /// this sprite.
+    sheet: usize,
+    asset_id: Option<u64>,
+    offset: (u32, u32),
+    size: (u32, u32),
+}
+
+#[derive(Debug, Error)]
+pub enum SpritesheetError {
+    #[error(transparent)]
+    Pack {
+        #[from]
+        source: PackError,
+    },
+
+    #[error(transparent)]
+    Image {
+        #[from]
+        source: UploadImageError,
+    },
+
+    #[error(transparent)]
+    WalkDir {
+        #[from]
+        source: walkdir::Error,
+    },
+
+    #[error(transparent)]
+    Json {
+        #[from]
+        source: serde_json::Error,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_relative_to_root() {
+        assert_eq!(
+            sprite_name(Path::new("assets"), Path::new("assets/icons/save.png")),
+            "icons/save"
+        );
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 };
 
 use fs_err as fs;
-use image::{codecs::png::PngEncoder, imageops, DynamicImage, GenericImageView, ImageError};
+use image::{imageops, DynamicImage, GenericImageView, ImageError};
 use packos::{InputItem, SimplePacker};
 use thiserror::Error;
 use walkdir::WalkDir;
@@ -15,13 +15,13 @@ use walkdir::WalkDir;
 use crate::{
     alpha_bleed::alpha_bleed,
     asset_name::AssetName,
-    auth_cookie::get_auth_cookie,
     codegen::perform_codegen,
+    commands::encode_png,
     data::{
         AssetId, Config, ConfigError, ImageSlice, InputManifest, Manifest, ManifestError, SyncInput,
     },
     dpi_scale,
-    options::{GlobalOptions, SyncOptions, SyncTarget},
+    options::{BleedOptions, GlobalOptions, SyncOptions, SyncTarget},
     roblox_web_api::{RobloxApiClient, RobloxApiError},
     sync_backend::{
         DebugSyncBackend, Error as SyncBackendError, LocalSyncBackend, NoneSyncBackend,
@@ -45,7 +45,7 @@ pub fn sync(global: GlobalOptions, options: SyncOptions) -> Result<(), SyncError
         None => env::current_dir()?,
     };
 
-    let mut api_client = RobloxApiClient::new(global.auth.or_else(get_auth_cookie));
+    let mut api_client = RobloxApiClient::from_options(&global)?;
 
     let mut session = SyncSession::new(&fuzzy_config_path)?;
 
@@ -59,7 +59,10 @@ pub fn sync(global: GlobalOptions, options: SyncOptions) -> Result<(), SyncError
             sync_session(
                 &mut session,
                 &options,
-                RobloxSyncBackend::new(&mut api_client, group_id),
+                RobloxSyncBackend::new(&mut api_client, group_id).moderation_retry(
+                    options.max_retries_on_moderation,
+                    options.moderation_fallbacks.clone(),
+                ),
             );
         }
         SyncTarget::Local => {
@@ -133,7 +136,7 @@ impl SyncSession {
     fn new(fuzzy_config_path: &Path) -> Result<Self, SyncError> {
         log::trace!("Starting new sync session");
 
-        let root_config = Config::read_from_folder_or_file(&fuzzy_config_path)?;
+        let root_config = Config::read_from_folder_or_file(fuzzy_config_path)?;
 
         log::trace!("Starting from config \"{}\"", root_config.name);
 
@@ -261,7 +264,7 @@ impl SyncSession {
                 for matching in filtered_paths {
                     let path = matching.into_path();
 
-                    let name = AssetName::from_paths(&root_config_path, &path);
+                    let name = AssetName::from_paths(root_config_path, &path);
                     log::trace!("Found input {}", name);
 
                     let path_info = dpi_scale::extract_path_info(&path);
@@ -277,9 +280,8 @@ impl SyncSession {
                     };
 
                     let already_found = inputs.insert(
-                        name.clone(),
+                        name,
                         SyncInput {
-                            name,
                             path,
                             path_without_dpi_scale: path_info.path_without_dpi_scale,
                             dpi_scale: path_info.dpi_scale,
@@ -377,7 +379,7 @@ impl SyncSession {
         for (i, packed_image) in packed_images.iter_mut().enumerate() {
             log::trace!("Bleeding image {}", i);
 
-            alpha_bleed(&mut packed_image.img);
+            alpha_bleed(&mut packed_image.img, &BleedOptions::default());
         }
 
         log::trace!("Syncing packed images...");
@@ -417,7 +419,7 @@ impl SyncSession {
         let mut images_by_id = HashMap::new();
 
         for name in group {
-            let input = &self.inputs[&name];
+            let input = &self.inputs[name];
             let img = image::load_from_memory(input.contents.as_slice())?;
 
             let input = InputItem::new(img.dimensions());
@@ -464,25 +466,11 @@ impl SyncSession {
         backend: &mut S,
         packed_image: &PackedImage,
     ) -> Result<(), SyncError> {
-        let mut encoded_image: Vec<u8> = Vec::new();
-
-        let (width, height) = packed_image.img.dimensions();
-
-        PngEncoder::new(&mut encoded_image)
-            .encode(
-                &packed_image.img.to_bytes(),
-                width,
-                height,
-                packed_image.img.color(),
-            )
-            .unwrap();
-
-        let hash = generate_asset_hash(&encoded_image);
+        let encoded_image = encode_png(&packed_image.img)?;
 
         let upload_data = UploadInfo {
             name: format!("spritesheet-{}", packed_image.index),
             contents: encoded_image,
-            hash,
         };
 
         let id = backend.upload(upload_data)?.id;
@@ -507,22 +495,16 @@ impl SyncSession {
 
         let mut img = image::load_from_memory(input.contents.as_slice())?;
 
-        alpha_bleed(&mut img);
-
-        let (width, height) = img.dimensions();
+        alpha_bleed(&mut img, &BleedOptions::default());
 
-        let mut encoded_image: Vec<u8> = Vec::new();
-        PngEncoder::new(&mut encoded_image)
-            .encode(&img.to_bytes(), width, height, img.color())
-            .unwrap();
+        let encoded_image = encode_png(&img)?;
 
         let upload_data = UploadInfo {
             name: input.human_name(),
             contents: encoded_image.to_vec(),
-            hash: input.hash.clone(),
         };
 
-        let id = if let Some(input_manifest) = self.original_manifest.inputs.get(&input_name) {
+        let id = if let Some(input_manifest) = self.original_manifest.inputs.get(input_name) {
             // This input existed during our last sync operation. We'll compare
             // the current state with the previous one to see if we need to take
             // action.
@@ -575,9 +557,7 @@ impl SyncSession {
     fn write_manifest(&self) -> Result<(), SyncError> {
         log::trace!("Generating new manifest");
 
-        let mut manifest = Manifest::default();
-
-        manifest.inputs = self
+        let inputs = self
             .inputs
             .iter()
             .map(|(name, input)| {
@@ -597,6 +577,7 @@ impl SyncSession {
             })
             .collect();
 
+        let manifest = Manifest { inputs };
         manifest.write_to_folder(self.root_config().folder())?;
 
         Ok(())
@@ -613,11 +594,7 @@ impl SyncSession {
         let mut compatible_codegen_groups = HashMap::new();
 
         for (input_name, input) in &self.inputs {
-            let output_path = input
-                .config
-                .codegen_path
-                .as_ref()
-                .map(|path| path.as_path());
+            let output_path = input.config.codegen_path.as_deref();
 
             let compat = CodegenCompatibility { output_path };
 
@@ -657,7 +634,7 @@ impl SyncSession {
             .collect();
 
         for id in known_ids {
-            writeln!(file, "{}", id.to_string())?;
+            writeln!(file, "{}", id)?;
         }
 
         file.flush()?;
@@ -672,7 +649,7 @@ impl SyncSession {
 
         log::debug!("Populating asset cache");
 
-        fs_err::create_dir_all(&cache_path)?;
+        fs_err::create_dir_all(cache_path)?;
 
         let known_ids: HashSet<u64> = self
             .inputs
@@ -686,7 +663,7 @@ impl SyncSession {
             .collect();
 
         // Clean up cache items that aren't present in our current project.
-        for entry in fs_err::read_dir(&cache_path)? {
+        for entry in fs_err::read_dir(cache_path)? {
             let entry = entry?;
             let path = entry.path();
             let metadata = fs_err::metadata(&path)?;
@@ -825,11 +802,11 @@ pub enum SyncError {
 
 impl SyncError {
     pub fn is_rate_limited(&self) -> bool {
-        match self {
+        matches!(
+            self,
             Self::Backend {
                 source: SyncBackendError::RateLimited,
-            } => true,
-            _ => false,
-        }
+            }
+        )
     }
 }
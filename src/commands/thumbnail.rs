@@ -0,0 +1,41 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::io;
+
+use crate::{
+    options::{GlobalOptions, ThumbnailOptions},
+    roblox_web_api::{RobloxApiClient, RobloxApiError},
+};
+
+pub fn thumbnail(global: GlobalOptions, options: ThumbnailOptions) -> Result<(), ThumbnailError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    let thumbnail = client.get_thumbnail(options.asset_id, &options.size)?;
+    fs::write(&options.out, thumbnail)?;
+
+    if !global.quiet {
+        eprintln!(
+            "Wrote the thumbnail of asset {} to {}",
+            options.asset_id,
+            options.out.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Error)]
+pub enum ThumbnailError {
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
@@ -0,0 +1,103 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::{borrow::Cow, io, path::Path};
+
+use crate::{
+    commands::{print_json, UploadOutcome, UploadRecord},
+    options::{GlobalOptions, OutputFormat, UploadAudioOptions},
+    roblox_web_api::{AssetUploader, AudioUploadData, RobloxApiClient, RobloxApiError},
+};
+
+pub fn upload_audio(
+    global: GlobalOptions,
+    options: UploadAudioOptions,
+) -> Result<(), UploadAudioError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    client.require_auth()?;
+
+    let outcome = upload_audio_file(
+        &mut client,
+        &options.path,
+        &options.name,
+        options.group_id,
+        global.dry_run,
+    )?;
+
+    if !global.quiet {
+        match outcome {
+            UploadOutcome::DryRun => eprintln!("Audio is ready to upload, but this is a dry run."),
+            _ => eprintln!("Audio uploaded successfully!"),
+        }
+    }
+
+    match global.output_format {
+        OutputFormat::Text => println!("{}", outcome),
+        OutputFormat::Json => print_json(&UploadRecord::succeeded(&options.path, outcome)),
+    }
+
+    Ok(())
+}
+
+/// Uploads the audio file at the given path. When `dry_run` is set, the file
+/// is read but not uploaded.
+pub fn upload_audio_file(
+    uploader: &mut impl AssetUploader,
+    path: &Path,
+    name: &str,
+    group_id: Option<u64>,
+    dry_run: bool,
+) -> Result<UploadOutcome, UploadAudioError> {
+    // Audio files are uploaded as-is. Unlike images, there's no processing
+    // that we need to do before handing them to Roblox.
+    let audio_data = fs::read(path)?;
+
+    if dry_run {
+        return Ok(UploadOutcome::DryRun);
+    }
+
+    let response = uploader.upload_audio(AudioUploadData {
+        audio_data: Cow::Owned(audio_data),
+        name,
+        group_id,
+    })?;
+
+    Ok(UploadOutcome::Uploaded(response.asset_id))
+}
+
+#[derive(Debug, Error)]
+pub enum UploadAudioError {
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::roblox_web_api::MockUploader;
+
+    #[test]
+    fn upload_file_as_is() {
+        let path = std::env::temp_dir().join("tarmac-upload_file_as_is.ogg");
+        fs::write(&path, b"OggS").unwrap();
+
+        let mut uploader = MockUploader::default();
+        let outcome = upload_audio_file(&mut uploader, &path, "theme", Some(5), false).unwrap();
+
+        assert_eq!(outcome, UploadOutcome::Uploaded(1));
+        assert_eq!(uploader.uploads[0].name, "theme");
+        assert_eq!(uploader.uploads[0].contents, b"OggS");
+        assert_eq!(uploader.uploads[0].group_id, Some(5));
+    }
+}
@@ -0,0 +1,712 @@
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use thiserror::Error;
+use walkdir::WalkDir;
+
+use fs_err as fs;
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use crate::{
+    auth_cookie::get_auth_cookie,
+    commands::{
+        content_hash, error_chain, is_image_path, print_json, render_description_template,
+        upload_image_file, AssetChange, DescriptionTemplateError, UploadImageError, UploadOutcome,
+        UploadRecord,
+    },
+    data::{UploadLock, UploadLockError, UploadLockGuard},
+    exit_code::{Classify, FailureKind},
+    options::{GlobalOptions, ImageUploadSettings, OutputFormat, UploadDirOptions},
+    roblox_web_api::{RobloxApiClient, RobloxApiError},
+    secret::Secret,
+};
+
+pub fn upload_dir(global: GlobalOptions, options: UploadDirOptions) -> Result<(), UploadDirError> {
+    let paths = find_images(&options.path)?;
+
+    upload_image_batch(
+        &global,
+        &paths,
+        &options.settings,
+        options.jobs,
+        options.fail_fast,
+        options.since,
+    )
+}
+
+/// Uploads each of the given images using up to `jobs` threads, then prints
+/// the path and asset ID of each one in order, followed by a summary.
+///
+/// Images are named after their file. Images that fail to upload are reported
+/// but don't stop the rest from being uploaded unless `fail_fast` is set.
+///
+/// Images with the same contents as an earlier image in the batch aren't
+/// uploaded themselves. They're given the earlier image's asset instead.
+///
+/// The summary also counts how each image's asset changed compared to
+/// `tarmac-lock.toml` from before the batch was uploaded.
+///
+/// When `since` is given, images last modified before then that are already
+/// in `tarmac-lock.toml` are reported as unchanged without being read.
+pub fn upload_image_batch(
+    global: &GlobalOptions,
+    paths: &[PathBuf],
+    settings: &ImageUploadSettings,
+    jobs: usize,
+    fail_fast: bool,
+    since: Option<SystemTime>,
+) -> Result<(), UploadDirError> {
+    upload_image_batch_with(
+        global,
+        paths,
+        settings,
+        jobs,
+        fail_fast,
+        since,
+        get_auth_cookie,
+    )
+}
+
+/// Like `upload_image_batch`, but looks the cookie up with `find_cookie`
+/// instead of in Roblox Studio when `--auth` isn't given.
+fn upload_image_batch_with<F>(
+    global: &GlobalOptions,
+    paths: &[PathBuf],
+    settings: &ImageUploadSettings,
+    jobs: usize,
+    fail_fast: bool,
+    since: Option<SystemTime>,
+    find_cookie: F,
+) -> Result<(), UploadDirError>
+where
+    F: FnOnce() -> Option<Secret>,
+{
+    // Check the template before uploading anything instead of letting every
+    // image fail with the same error.
+    if let Some(template) = &settings.description_template {
+        render_description_template(template, "", Path::new(""))?;
+    }
+
+    // Every worker gets a clone of this client, so the cookie is only looked
+    // up once for the whole batch.
+    let client = RobloxApiClient::from_options_with(global, find_cookie)?;
+
+    client.require_auth()?;
+
+    // Held until we're done with the lock file, even if uploads fail.
+    let _guard = if settings.concurrency_safe {
+        Some(UploadLockGuard::acquire(".")?)
+    } else {
+        None
+    };
+
+    // Kept to compare against once everything has been uploaded.
+    let previous_lock = UploadLock::read_from_folder(".")?;
+
+    let skipped: Vec<Option<u64>> = paths
+        .iter()
+        .map(|path| {
+            let since = since?;
+
+            if modified_since(path, since) {
+                None
+            } else {
+                previous_lock.id_as(path, settings.asset_type, &settings.name_prefix)
+            }
+        })
+        .collect();
+
+    let hashed = hash_images(paths, &skipped);
+    let hashes: Vec<Option<String>> = hashed
+        .iter()
+        .map(|hash| hash.as_ref().and_then(|hash| hash.as_ref().ok()).cloned())
+        .collect();
+
+    // Images that couldn't be read fail without being uploaded.
+    let results: Vec<Option<Result<UploadOutcome, UploadDirError>>> = paths
+        .iter()
+        .zip(hashed)
+        .map(|(path, hash)| match hash {
+            Some(Err(source)) => Some(Err(UploadDirError::Upload {
+                path: path.clone(),
+                name: asset_name_for(path),
+                source: Box::new(source.into()),
+            })),
+            _ => None,
+        })
+        .collect();
+    let read_failed = results.iter().any(Option::is_some);
+
+    let originals = find_originals(&hashes);
+    let to_upload: Vec<usize> = (0..paths.len())
+        .filter(|&index| {
+            originals[index].is_none() && skipped[index].is_none() && results[index].is_none()
+        })
+        .collect();
+
+    if since.is_some() {
+        log::info!(
+            "Skipping {} image(s) that weren't modified since the time given to --since",
+            skipped.iter().flatten().count()
+        );
+    }
+
+    let batch = UploadBatch {
+        paths,
+        to_upload: &to_upload,
+        settings,
+        dry_run: global.dry_run,
+        fail_fast,
+        lock: Mutex::new(previous_lock.clone()),
+        next_index: AtomicUsize::new(0),
+        stopped: AtomicBool::new(fail_fast && read_failed),
+        results: Mutex::new(results),
+        progress: UploadProgress::new(to_upload.len(), global),
+    };
+
+    let workers = jobs.max(1).min(to_upload.len());
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let client = client.clone();
+                let batch = &batch;
+
+                scope.spawn(move || batch.run_worker(client))
+            })
+            .collect();
+
+        // Every worker is joined, even after one fails, so that a panic in a
+        // later worker is reported instead of resurfacing from the scope.
+        handles
+            .into_iter()
+            .map(|handle| match handle.join() {
+                Ok(result) => result.map_err(UploadDirError::from),
+                Err(_) => Err(UploadDirError::WorkerPanicked),
+            })
+            .fold(Ok(()), Result::and)
+    })?;
+
+    batch.progress.finish();
+
+    let mut results = batch.results.into_inner().unwrap();
+
+    for (result, skipped) in results.iter_mut().zip(&skipped) {
+        if let Some(id) = skipped {
+            *result = Some(Ok(UploadOutcome::Unchanged(*id)));
+        }
+    }
+    let mut lock = batch.lock.into_inner().unwrap();
+
+    for (index, original) in originals.iter().enumerate() {
+        let original = match original {
+            Some(original) => *original,
+            None => continue,
+        };
+
+        results[index] = match &results[original] {
+            Some(Ok(outcome)) => {
+                if let (Some(id), Some(hash)) = (outcome.id(), &hashes[index]) {
+                    lock.insert(&paths[index], hash.clone(), id, settings.asset_type);
+                    lock.set_tags(&paths[index], &settings.tags);
+                    lock.set_name_prefix(&paths[index], &settings.name_prefix);
+                }
+
+                Some(Ok(*outcome))
+            }
+            Some(Err(_)) => Some(Err(UploadDirError::OriginalFailed {
+                path: paths[index].clone(),
+                original: paths[original].clone(),
+            })),
+            None => None,
+        };
+    }
+
+    // Duplicates reuse their original's upload, and unchanged images may have
+    // been given new tags, without anything being saved yet.
+    if lock.has_changes() && !global.dry_run {
+        lock.write_to_folder(".")?;
+    }
+
+    // The manifest is written even if some uploads failed, so that the ones
+    // that succeeded aren't lost.
+    if let Some(manifest_path) = settings.manifest_out.as_ref().filter(|_| !global.dry_run) {
+        let succeeded = paths
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| matches!(result, Some(Ok(_))))
+            .map(|(path, _)| path.as_path());
+
+        lock.write_manifest(succeeded, manifest_path)?;
+    }
+
+    let mut summary = UploadSummary::default();
+    let mut records = Vec::new();
+
+    // Results are reported in the order the paths were given in, regardless
+    // of the order that the uploads finished in.
+    for ((path, result), original) in paths.iter().zip(results).zip(&originals) {
+        match result {
+            Some(Ok(outcome)) => {
+                let change = outcome
+                    .id()
+                    .map(|id| AssetChange::between(previous_lock.id_of(path), id));
+
+                match global.output_format {
+                    OutputFormat::Text => println!("{}\t{}", path.display(), outcome),
+                    OutputFormat::Json => {
+                        records.push(UploadRecord::succeeded(path, outcome).with_change(change))
+                    }
+                }
+
+                match change {
+                    Some(AssetChange::New) => summary.new += 1,
+                    Some(AssetChange::Updated { .. }) => summary.updated += 1,
+                    Some(AssetChange::Unchanged) => summary.kept += 1,
+                    None => {}
+                }
+
+                match outcome {
+                    _ if original.is_some() => summary.reused += 1,
+                    UploadOutcome::Uploaded(_) => summary.uploaded += 1,
+                    UploadOutcome::Unchanged(_) => summary.unchanged += 1,
+                    UploadOutcome::DryRun => summary.dry_run += 1,
+                }
+            }
+            Some(Err(error)) => {
+                records.push(UploadRecord::failed(path, &error));
+
+                if fail_fast {
+                    if global.output_format == OutputFormat::Json {
+                        print_json(&records);
+                    }
+
+                    return Err(error);
+                }
+
+                summary.failed.push(error);
+            }
+
+            // This image wasn't attempted because another one failed first.
+            None => {}
+        }
+    }
+
+    if global.output_format == OutputFormat::Json {
+        print_json(&records);
+    }
+
+    summary.report(global.quiet);
+
+    if summary.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(UploadDirError::HadErrors {
+            error_count: summary.failed.len(),
+            failure_kind: FailureKind::common(summary.failed.iter().map(Classify::failure_kind)),
+        })
+    }
+}
+
+/// Whether the file at the given path was modified at or after `since`. Files
+/// whose modification time can't be read count as modified, and so do files
+/// modified in the future, since that's most likely clock skew.
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified >= since,
+        Err(_) => true,
+    }
+}
+
+/// Hashes the contents of each image that isn't skipped. Images that can't be
+/// read are given the error instead, so that it can be reported for them.
+fn hash_images(paths: &[PathBuf], skipped: &[Option<u64>]) -> Vec<Option<io::Result<String>>> {
+    paths
+        .iter()
+        .zip(skipped)
+        .map(|(path, skipped)| match skipped {
+            Some(_) => None,
+            None => Some(fs::read(path).map(|data| content_hash(&data))),
+        })
+        .collect()
+}
+
+/// Finds the images that have the same contents as an earlier image, given the
+/// hash of each image. Each image is paired with the index of the first image
+/// with the same hash, or `None` if it's the first. Images that couldn't be
+/// hashed are never paired.
+fn find_originals(hashes: &[Option<String>]) -> Vec<Option<usize>> {
+    let mut first_with_hash = HashMap::new();
+
+    hashes
+        .iter()
+        .enumerate()
+        .map(|(index, hash)| {
+            let hash = hash.as_ref()?;
+            let first = *first_with_hash.entry(hash).or_insert(index);
+
+            Some(first).filter(|&first| first != index)
+        })
+        .collect()
+}
+
+/// The state shared between the threads uploading a batch of images.
+struct UploadBatch<'a> {
+    paths: &'a [PathBuf],
+
+    /// The indices of the paths that need to be uploaded, leaving out images
+    /// that have the same contents as an earlier one.
+    to_upload: &'a [usize],
+    settings: &'a ImageUploadSettings,
+    dry_run: bool,
+    fail_fast: bool,
+    lock: Mutex<UploadLock>,
+    next_index: AtomicUsize,
+    stopped: AtomicBool,
+    results: Mutex<Vec<Option<Result<UploadOutcome, UploadDirError>>>>,
+    progress: UploadProgress,
+}
+
+impl UploadBatch<'_> {
+    /// Uploads images until there are none left, or until an upload fails
+    /// when `fail_fast` is set.
+    fn run_worker(&self, mut client: RobloxApiClient) -> Result<(), UploadLockError> {
+        while !self.stopped.load(Ordering::SeqCst) {
+            let next = self.next_index.fetch_add(1, Ordering::SeqCst);
+            let index = match self.to_upload.get(next) {
+                Some(index) => *index,
+                None => break,
+            };
+            let path = &self.paths[index];
+
+            let name = asset_name_for(path);
+            let result = upload_image_file(
+                &mut client,
+                &self.lock,
+                path,
+                &name,
+                self.settings,
+                self.dry_run,
+            )
+            .map_err(|source| UploadDirError::Upload {
+                path: path.clone(),
+                name,
+                source: Box::new(source),
+            });
+
+            match &result {
+                Ok(UploadOutcome::Uploaded(_)) => {
+                    // Save after every upload so that progress isn't lost if a
+                    // later upload fails or Tarmac is interrupted.
+                    self.lock.lock().unwrap().write_to_folder(".")?;
+                }
+                Ok(UploadOutcome::Unchanged(_)) | Ok(UploadOutcome::DryRun) => {}
+                Err(_) => {
+                    if self.fail_fast {
+                        self.stopped.store(true, Ordering::SeqCst);
+                    }
+                }
+            }
+
+            self.progress.completed(path);
+            self.results.lock().unwrap()[index] = Some(result);
+        }
+
+        Ok(())
+    }
+}
+
+/// Shows how many images in a batch have been uploaded so far. When stderr
+/// isn't a terminal, or the output is meant for machines, a line is logged for
+/// each image instead of drawing a progress bar.
+struct UploadProgress {
+    bar: Option<ProgressBar>,
+    total: usize,
+    completed: AtomicUsize,
+}
+
+impl UploadProgress {
+    fn new(total: usize, global: &GlobalOptions) -> Self {
+        let target = ProgressDrawTarget::stderr();
+        let use_bar =
+            global.output_format == OutputFormat::Text && !global.quiet && !target.is_hidden();
+
+        let bar = if use_bar {
+            let style = ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}")
+                .expect("progress bar template is invalid");
+
+            Some(ProgressBar::with_draw_target(Some(total as u64), target).with_style(style))
+        } else {
+            None
+        };
+
+        Self {
+            bar,
+            total,
+            completed: AtomicUsize::new(0),
+        }
+    }
+
+    /// Records that an image finished uploading, successfully or not.
+    fn completed(&self, path: &Path) {
+        let completed = self.completed.fetch_add(1, Ordering::SeqCst) + 1;
+
+        match &self.bar {
+            Some(bar) => {
+                bar.set_message(path.display().to_string());
+                bar.inc(1);
+            }
+            None => log::info!("[{}/{}] {}", completed, self.total, path.display()),
+        }
+    }
+
+    fn finish(&self) {
+        if let Some(bar) = &self.bar {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// Finds every image in the given directory, in a stable order.
+pub fn find_images(dir: &Path) -> Result<Vec<PathBuf>, walkdir::Error> {
+    let mut paths = Vec::new();
+
+    for entry in WalkDir::new(dir).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+
+        if entry.file_type().is_file() && is_image_path(entry.path()) {
+            paths.push(entry.into_path());
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Images are named after their file, without its extension.
+pub fn asset_name_for(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+struct UploadSummary {
+    uploaded: usize,
+    unchanged: usize,
+    dry_run: usize,
+
+    /// Images that were given the asset of an earlier image with the same
+    /// contents instead of being uploaded.
+    reused: usize,
+
+    /// How many images got a new asset, a different asset, or kept their asset
+    /// compared to `tarmac-lock.toml` from before the upload.
+    new: usize,
+    updated: usize,
+    kept: usize,
+
+    failed: Vec<UploadDirError>,
+}
+
+impl UploadSummary {
+    /// Prints the summary to stderr. When `quiet` is set, only failures are
+    /// printed.
+    fn report(&self, quiet: bool) {
+        if !quiet {
+            if self.dry_run > 0 {
+                eprintln!(
+                    "Dry run: {} image(s) would have been uploaded.",
+                    self.dry_run
+                );
+            }
+
+            eprintln!(
+                "Uploaded {} image(s), skipped {} unchanged image(s), {} failed.",
+                self.uploaded,
+                self.unchanged,
+                self.failed.len()
+            );
+
+            if self.reused > 0 {
+                eprintln!(
+                    "Reused an earlier upload for {} image(s) with identical contents.",
+                    self.reused
+                );
+            }
+
+            if self.new + self.updated + self.kept > 0 {
+                eprintln!(
+                    "Compared to tarmac-lock.toml: {} new, {} updated, {} skipped.",
+                    self.new, self.updated, self.kept
+                );
+            }
+        }
+
+        for error in &self.failed {
+            eprintln!("  {}", error_chain(error));
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UploadDirError {
+    #[error("Upload completed, but with {error_count} error(s)")]
+    HadErrors {
+        error_count: usize,
+
+        /// The kind of failure that every error was, if they were all the
+        /// same kind.
+        failure_kind: FailureKind,
+    },
+
+    #[error("Couldn't upload {} as '{name}'", .path.display())]
+    Upload {
+        path: PathBuf,
+        name: String,
+        source: Box<UploadImageError>,
+    },
+
+    #[error(
+        "Couldn't upload {} because {}, which has the same contents, failed to upload",
+        .path.display(),
+        .original.display()
+    )]
+    OriginalFailed { path: PathBuf, original: PathBuf },
+
+    #[error("An upload worker crashed, so some images may not have been uploaded")]
+    WorkerPanicked,
+
+    #[error(transparent)]
+    DescriptionTemplate {
+        #[from]
+        source: DescriptionTemplateError,
+    },
+
+    #[error(transparent)]
+    WalkDir {
+        #[from]
+        source: walkdir::Error,
+    },
+
+    #[error(transparent)]
+    UploadLock {
+        #[from]
+        source: UploadLockError,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use structopt::StructOpt;
+
+    use crate::options::{Options, Subcommand};
+
+    #[test]
+    fn originals_of_duplicates() {
+        let hashes = [
+            Some("a".to_owned()),
+            Some("b".to_owned()),
+            Some("a".to_owned()),
+            None,
+            None,
+            Some("a".to_owned()),
+            Some("b".to_owned()),
+        ];
+
+        assert_eq!(
+            find_originals(&hashes),
+            vec![None, None, Some(0), None, None, Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn unreadable_images_keep_their_error() {
+        let readable = std::env::temp_dir().join("tarmac-unreadable_images_keep_their_error.png");
+        fs::write(&readable, b"image").unwrap();
+        let missing =
+            std::env::temp_dir().join("tarmac-unreadable_images_keep_their_error-missing.png");
+
+        let paths = [readable.clone(), missing, readable];
+        let hashed = hash_images(&paths, &[None, None, Some(1)]);
+
+        assert_eq!(
+            hashed[0].as_ref().unwrap().as_ref().unwrap(),
+            &content_hash(b"image")
+        );
+        assert_eq!(
+            hashed[1].as_ref().unwrap().as_ref().unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(hashed[2].is_none());
+    }
+
+    #[test]
+    fn one_cookie_lookup_per_batch() {
+        let folder = std::env::temp_dir().join(format!(
+            "tarmac-one_cookie_lookup_per_batch-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&folder).unwrap();
+
+        // Each image is different so that none are skipped as duplicates.
+        let paths: Vec<PathBuf> = (0..50u8)
+            .map(|index| {
+                let path = folder.join(format!("{}.png", index));
+                image::RgbaImage::from_pixel(4, 4, image::Rgba([index, 0, 0, 255]))
+                    .save(&path)
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        let options = Options::from_iter(&["tarmac", "--dry-run", "--quiet", "upload-dir", "."]);
+        let mut global = options.global;
+        global.auth = None;
+        let settings = match options.command {
+            Subcommand::UploadDir(options) => options.settings,
+            _ => unreachable!(),
+        };
+
+        let lookups = AtomicUsize::new(0);
+        let result = upload_image_batch_with(&global, &paths, &settings, 8, true, None, || {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            Some(Secret::new(
+                "_|WARNING:-DO-NOT-SHARE-THIS.cookie".to_owned(),
+            ))
+        });
+
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn name_from_file_stem() {
+        assert_eq!(asset_name_for(Path::new("icons/save.png")), "save");
+        assert_eq!(asset_name_for(Path::new("logo.large.svg")), "logo.large");
+    }
+}
@@ -1,48 +1,1499 @@
 use fs_err as fs;
 
-use image::{codecs::png::PngEncoder, GenericImageView};
+use image::{
+    codecs::png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+    imageops::FilterType,
+    ColorType, DynamicImage, GenericImageView, ImageError, ImageFormat, ImageResult, Rgb,
+};
+use thiserror::Error;
+use walkdir::WalkDir;
 
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
 
 use crate::{
     alpha_bleed::alpha_bleed,
-    auth_cookie::get_auth_cookie,
-    options::{GlobalOptions, UploadImageOptions},
-    roblox_web_api::{ImageUploadData, RobloxApiClient},
+    alpha_channel::{flatten_alpha, premultiply_alpha},
+    commands::{
+        asset_name_for, print_json, upload_audio, upload_image_batch, upload_image_list,
+        UploadAudioError, UploadDirError, UploadListError, UploadRecord,
+    },
+    data::{UploadLock, UploadLockError, UploadLockGuard},
+    glob::{self, Glob},
+    options::{
+        BleedOptions, GlobalOptions, HexColor, ImageUploadSettings, OutputFormat, PngCompression,
+        PngFilter, UploadAudioOptions, UploadImageOptions,
+    },
+    roblox_web_api::{AssetUploader, ImageUploadData, RobloxApiClient, RobloxApiError},
+    svg::{rasterize_svg, SvgError},
+    uploader::{Tarmac, TarmacOptions},
 };
 
-pub fn upload_image(global: GlobalOptions, options: UploadImageOptions) {
-    let auth = global
-        .auth
-        .or_else(get_auth_cookie)
-        .expect("no auth cookie found");
+/// How an image read from stdin is referred to in messages.
+const STDIN_PATH: &str = "<stdin>";
 
-    let image_data = fs::read(options.path).expect("couldn't read input file");
+/// The kinds of asset that can be uploaded, picked from the extension of the
+/// file being uploaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UploadKind {
+    Image,
+    VectorImage,
+    Audio,
+    Model,
 
-    let mut img = image::load_from_memory(&image_data).expect("couldn't load image");
+    /// An image in a format that Tarmac recognizes, but can't decode.
+    UndecodableImage,
+}
 
-    alpha_bleed(&mut img);
+impl UploadKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
 
-    let (width, height) = img.dimensions();
+        match extension.as_str() {
+            "png" | "jpg" | "jpeg" | "tga" | "bmp" | "webp" => Some(UploadKind::Image),
+            "svg" => Some(UploadKind::VectorImage),
+            "mp3" | "ogg" => Some(UploadKind::Audio),
+            "fbx" => Some(UploadKind::Model),
+            "avif" | "heic" | "heif" => Some(UploadKind::UndecodableImage),
+            _ => None,
+        }
+    }
+}
 
-    let mut encoded_image: Vec<u8> = Vec::new();
-    PngEncoder::new(&mut encoded_image)
-        .encode(&img.to_bytes(), width, height, img.color())
-        .unwrap();
+/// Whether the file at the given path is an image that `upload_image_file`
+/// knows how to upload.
+pub fn is_image_path(path: &Path) -> bool {
+    matches!(
+        UploadKind::from_path(path),
+        Some(UploadKind::Image) | Some(UploadKind::VectorImage)
+    )
+}
+
+pub fn upload_image(
+    global: GlobalOptions,
+    options: UploadImageOptions,
+) -> Result<(), UploadImageError> {
+    if let Some(list_path) = &options.from_file {
+        return upload_image_list(&global, list_path, &options.settings, options.fail_fast)
+            .map_err(|source| Box::new(source).into());
+    }
+
+    let path = match options.path {
+        Some(path) if !options.stdin && path != Path::new("-") => path,
+        _ => {
+            let outcome = upload_image_stdin(&global, options.name, &options.settings)?;
+            report_outcome(&global, Path::new(STDIN_PATH), outcome);
+
+            return Ok(());
+        }
+    };
+
+    let pattern = path.to_string_lossy();
+
+    if glob::is_pattern(&pattern) {
+        // Every image would be given the same name otherwise.
+        if options.name.is_some() {
+            return Err(UploadImageError::NameWithGlob {
+                pattern: pattern.into_owned(),
+            });
+        }
+
+        let paths = expand_image_glob(&pattern)?;
+
+        return upload_image_batch(&global, &paths, &options.settings, 1, false, None)
+            .map_err(|source| Box::new(source).into());
+    }
+
+    let name = options
+        .name
+        .clone()
+        .unwrap_or_else(|| asset_name_for(&path));
+
+    check_name(&path, &name)?;
+
+    match UploadKind::from_path(&path) {
+        Some(UploadKind::Image) | Some(UploadKind::VectorImage) => {}
+        Some(UploadKind::Audio) => {
+            let audio_options = UploadAudioOptions {
+                path,
+                name,
+                group_id: None,
+            };
+
+            return Ok(upload_audio(global, audio_options)?);
+        }
+        Some(UploadKind::Model) => {
+            return Err(UploadImageError::ModelsUnsupported { path });
+        }
+        Some(UploadKind::UndecodableImage) => {
+            return Err(UploadImageError::ImageFormatUnsupported { path });
+        }
+        None => {
+            return Err(UploadImageError::UnsupportedFileType { path });
+        }
+    }
+
+    // Held until we're done with the lock file, even if the upload fails.
+    let _guard = if options.settings.concurrency_safe {
+        Some(UploadLockGuard::acquire(".")?)
+    } else {
+        None
+    };
+
+    let manifest_out = options.settings.manifest_out.clone();
+
+    let mut tarmac = Tarmac::new(TarmacOptions {
+        global: global.clone(),
+        settings: options.settings,
+        lock_folder: PathBuf::from("."),
+    })?;
+
+    let result = tarmac.upload_file_named(&path, &name)?;
+
+    if let Some(manifest_path) = manifest_out.filter(|_| !global.dry_run) {
+        tarmac
+            .lock()
+            .write_manifest(Some(path.as_path()), &manifest_path)?;
+    }
+
+    report_outcome(&global, &path, result.outcome);
+
+    Ok(())
+}
+
+/// Prints what happened to a single uploaded image.
+fn report_outcome(global: &GlobalOptions, path: &Path, outcome: UploadOutcome) {
+    if !global.quiet {
+        match outcome {
+            UploadOutcome::Uploaded(_) => eprintln!("Image uploaded successfully!"),
+            UploadOutcome::Unchanged(id) => eprintln!(
+                "Image is unchanged since it was uploaded as rbxassetid://{}, skipping upload.",
+                id
+            ),
+            UploadOutcome::DryRun => eprintln!("Image is ready to upload, but this is a dry run."),
+        }
+    }
+
+    match global.output_format {
+        OutputFormat::Text => println!("{}", outcome),
+        OutputFormat::Json => print_json(&UploadRecord::succeeded(path, outcome)),
+    }
+}
+
+/// Finds every image matching the given glob pattern. Patterns that don't
+/// match any images are an error, since they're almost certainly a mistake.
+pub fn expand_image_glob(pattern: &str) -> Result<Vec<PathBuf>, UploadImageError> {
+    let glob = Glob::new(pattern).map_err(|source| UploadImageError::InvalidGlob {
+        pattern: pattern.to_owned(),
+        source,
+    })?;
+
+    // Patterns like `*.png` have no fixed prefix, so we search the current
+    // directory and match against paths relative to it.
+    let prefix = glob.get_prefix();
+    let root = if prefix.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        prefix.as_path()
+    };
+
+    if !root.exists() {
+        return Err(UploadImageError::NoMatches {
+            pattern: pattern.to_owned(),
+        });
+    }
+
+    let mut paths = Vec::new();
+
+    for entry in WalkDir::new(root).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+        let path = entry
+            .path()
+            .strip_prefix(".")
+            .unwrap_or_else(|_| entry.path());
+
+        if entry.file_type().is_file() && is_image_path(path) && glob.is_match(path) {
+            paths.push(path.to_owned());
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(UploadImageError::NoMatches {
+            pattern: pattern.to_owned(),
+        });
+    }
+
+    Ok(paths)
+}
+
+/// What happened to an image given to `upload_image_file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadOutcome {
+    /// The image was uploaded to the asset with the given ID.
+    Uploaded(u64),
+
+    /// The image hasn't changed since it was uploaded to the asset with the
+    /// given ID, so it wasn't uploaded again.
+    Unchanged(u64),
+
+    /// The image was ready to upload, but `--dry-run` was passed, so it wasn't
+    /// uploaded and has no asset ID.
+    DryRun,
+}
+
+impl UploadOutcome {
+    pub fn id(self) -> Option<u64> {
+        match self {
+            UploadOutcome::Uploaded(id) | UploadOutcome::Unchanged(id) => Some(id),
+            UploadOutcome::DryRun => None,
+        }
+    }
+}
+
+impl fmt::Display for UploadOutcome {
+    /// Prints the asset ID, or a placeholder if there isn't one because of a
+    /// dry run.
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.id() {
+            Some(id) => write!(formatter, "{}", id),
+            None => write!(formatter, "(dry run)"),
+        }
+    }
+}
+
+/// Decodes, processes, and uploads a single image, recording the upload in
+/// the given lock. The lock is not written back to disk. When `dry_run` is
+/// set, everything but the upload itself happens.
+///
+/// The lock is shared behind a mutex so that several images can be uploaded
+/// at once. It's only held while reading or recording an upload.
+pub fn upload_image_file(
+    uploader: &mut impl AssetUploader,
+    lock: &Mutex<UploadLock>,
+    path: &Path,
+    name: &str,
+    settings: &ImageUploadSettings,
+    dry_run: bool,
+) -> Result<UploadOutcome, UploadImageError> {
+    check_name(path, name)?;
+    let name = format!("{}{}", settings.name_prefix, name);
+
+    if UploadKind::from_path(path) == Some(UploadKind::UndecodableImage) {
+        return Err(UploadImageError::ImageFormatUnsupported {
+            path: path.to_owned(),
+        });
+    }
+
+    if !is_image_path(path) {
+        return Err(UploadImageError::UnsupportedFileType {
+            path: path.to_owned(),
+        });
+    }
+
+    let image_data = fs::read(path)?;
+    let hash = content_hash(&image_data);
+
+    if !settings.force {
+        let existing =
+            lock.lock()
+                .unwrap()
+                .get(path, &hash, settings.asset_type, &settings.name_prefix);
+
+        if let Some(id) = existing {
+            log::debug!(
+                "{} is unchanged since it was uploaded as {}",
+                path.display(),
+                id
+            );
+
+            if !settings.tags.is_empty() {
+                lock.lock().unwrap().set_tags(path, &settings.tags);
+            }
+
+            return Ok(UploadOutcome::Unchanged(id));
+        }
+    }
+
+    let img = decode_image(path, &image_data, settings.svg_size)?;
+    let outcome = upload_decoded_image(uploader, img, path, &name, settings, dry_run)?;
+
+    if let UploadOutcome::Uploaded(id) = outcome {
+        let mut lock = lock.lock().unwrap();
+        lock.insert(path, hash, id, settings.asset_type);
+        lock.set_tags(path, &settings.tags);
+        lock.set_name_prefix(path, &settings.name_prefix);
+    }
+
+    Ok(outcome)
+}
+
+/// The hash that `tarmac-lock.toml` identifies the contents of a file by.
+pub fn content_hash(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+/// Uploads an image read from stdin, which has to be given a name since it
+/// doesn't have a file name. The image isn't recorded in `tarmac-lock.toml`.
+fn upload_image_stdin(
+    global: &GlobalOptions,
+    name: Option<String>,
+    settings: &ImageUploadSettings,
+) -> Result<UploadOutcome, UploadImageError> {
+    let path = Path::new(STDIN_PATH);
+    let name = name.unwrap_or_default();
+    check_name(path, &name)?;
+    let name = format!("{}{}", settings.name_prefix, name);
+
+    let mut image_data = Vec::new();
+    io::stdin().lock().read_to_end(&mut image_data)?;
+
+    if image_data.is_empty() {
+        return Err(UploadImageError::EmptyStdin);
+    }
+
+    let img = decode_image(path, &image_data, settings.svg_size)?;
+
+    let mut client = RobloxApiClient::from_options(global)?;
 
-    let mut client = RobloxApiClient::new(Some(auth));
+    client.require_auth()?;
+
+    upload_decoded_image(
+        &mut client,
+        img,
+        path,
+        name.as_str(),
+        settings,
+        global.dry_run,
+    )
+}
+
+/// Processes a decoded image according to the given settings, then uploads
+/// it. `path` is only used to describe the image in logs.
+fn upload_decoded_image(
+    uploader: &mut impl AssetUploader,
+    img: DynamicImage,
+    path: &Path,
+    name: &str,
+    settings: &ImageUploadSettings,
+    dry_run: bool,
+) -> Result<UploadOutcome, UploadImageError> {
+    let description = match &settings.description_template {
+        Some(template) => render_description_template(template, name, path)?,
+        None => settings.description.clone(),
+    };
+
+    let encoded_image = prepare_image(img, settings)?;
+
+    if let Some(output_dir) = &settings.output_dir {
+        // Images from stdin have no path to mirror, so they're named after
+        // their asset instead.
+        let source = if path == Path::new(STDIN_PATH) {
+            Path::new(name)
+        } else {
+            path
+        };
+
+        write_output_image(output_dir, source, &encoded_image, settings.strict)?;
+    }
 
     let upload_data = ImageUploadData {
-        image_data: Cow::Owned(encoded_image.to_vec()),
-        name: &options.name,
-        description: &options.description,
+        image_data: Cow::Borrowed(&encoded_image),
+        name,
+        description: &description,
         group_id: None,
+        asset_type: settings.asset_type,
+    };
+
+    if dry_run {
+        log::info!("Dry run: not uploading {}", path.display());
+        return Ok(UploadOutcome::DryRun);
+    }
+
+    log::debug!(
+        "Uploading {} as '{}' ({} bytes)",
+        path.display(),
+        name,
+        upload_data.image_data.len()
+    );
+
+    let response = uploader.upload_image(upload_data)?;
+
+    if settings.verify {
+        verify_upload(uploader, path, &encoded_image, response.backing_asset_id)?;
+    }
+
+    Ok(UploadOutcome::Uploaded(response.backing_asset_id))
+}
+
+/// The path in `output_dir` that the processed version of the image at the
+/// given path is written to. Only the normal parts of the path are kept, so
+/// absolute paths and paths with `..` still end up inside `output_dir`.
+fn output_image_path(output_dir: &Path, path: &Path) -> PathBuf {
+    let relative: PathBuf = path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)))
+        .collect();
+
+    output_dir.join(relative).with_extension("png")
+}
+
+/// Writes a processed image to `--output-dir`. Failing to write it only logs
+/// a warning, unless `strict` is set.
+fn write_output_image(
+    output_dir: &Path,
+    path: &Path,
+    encoded_image: &[u8],
+    strict: bool,
+) -> Result<(), UploadImageError> {
+    let output_path = output_image_path(output_dir, path);
+
+    let result = output_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&output_path, encoded_image));
+
+    match result {
+        Ok(()) => {
+            log::debug!("Wrote processed image to {}", output_path.display());
+            Ok(())
+        }
+        Err(source) if strict => Err(UploadImageError::WriteOutput {
+            path: output_path,
+            source,
+        }),
+        Err(err) => {
+            log::warn!(
+                "Couldn't write processed image to {}: {}",
+                output_path.display(),
+                err
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Downloads an uploaded image and checks that its pixels are the same as the
+/// pixels of the PNG we uploaded. Roblox may encode the image differently, so
+/// the files themselves aren't compared.
+fn verify_upload(
+    uploader: &mut impl AssetUploader,
+    path: &Path,
+    uploaded: &[u8],
+    asset_id: u64,
+) -> Result<(), UploadImageError> {
+    let stored = uploader.download_image(asset_id)?;
+
+    let uploaded =
+        image::load_from_memory(uploaded).map_err(|source| UploadImageError::Encode { source })?;
+
+    let matches = match image::load_from_memory(&stored) {
+        Ok(stored) => {
+            stored.dimensions() == uploaded.dimensions()
+                && stored.to_rgba8().as_raw() == uploaded.to_rgba8().as_raw()
+        }
+        Err(err) => {
+            log::debug!("Couldn't decode image {} from Roblox: {}", asset_id, err);
+            false
+        }
+    };
+
+    if matches {
+        log::debug!("Verified that {} was stored as uploaded", path.display());
+        Ok(())
+    } else {
+        Err(UploadImageError::VerifyMismatch {
+            path: path.to_owned(),
+            asset_id,
+        })
+    }
+}
+
+/// Checks that a decoded image can be uploaded with the given settings, then
+/// processes it and encodes it as the PNG that would be uploaded.
+pub fn prepare_image(
+    mut img: DynamicImage,
+    settings: &ImageUploadSettings,
+) -> Result<Vec<u8>, UploadImageError> {
+    let (width, height) = img.dimensions();
+    let too_large = width > settings.max_size || height > settings.max_size;
+
+    if too_large && !settings.resize {
+        return Err(UploadImageError::TooLarge {
+            width,
+            height,
+            max_size: settings.max_size,
+        });
+    }
+
+    match settings.flatten {
+        Some(HexColor(background)) => img = flatten_alpha(&img, Rgb(background)),
+        None if !settings.no_alpha_bleed => alpha_bleed(&mut img, &settings.bleed),
+        None => {}
+    }
+
+    if too_large {
+        img = downscale_to_fit(&img, settings.max_size);
+        log::info!(
+            "Downscaled image from {}x{} to {}x{}",
+            width,
+            height,
+            img.width(),
+            img.height()
+        );
+    }
+
+    // Premultiplying has to happen last, since resizing expects straight alpha.
+    if settings.premultiply_alpha {
+        premultiply_alpha(&mut img);
+    }
+
+    encode_for_upload(&img, settings)
+}
+
+/// Fills in the `{name}` and `{path}` placeholders of a description template.
+/// Any other placeholder is an error, so that a typo doesn't end up in the
+/// descriptions of uploaded assets.
+pub fn render_description_template(
+    template: &str,
+    name: &str,
+    path: &Path,
+) -> Result<String, DescriptionTemplateError> {
+    let invalid = |placeholder: &str| DescriptionTemplateError {
+        template: template.to_owned(),
+        placeholder: placeholder.to_owned(),
+    };
+
+    let mut description = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        description.push_str(&rest[..start]);
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(invalid(&rest[start..])),
+        };
+
+        match &rest[start + 1..end] {
+            "name" => description.push_str(name),
+            "path" => description.push_str(&path.to_string_lossy().replace('\\', "/")),
+            placeholder => return Err(invalid(placeholder)),
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    description.push_str(rest);
+
+    Ok(description)
+}
+
+#[derive(Debug, Error)]
+#[error(
+    "Description template {template:?} has an invalid placeholder {placeholder:?}. \
+     Valid placeholders are {{name}} and {{path}}."
+)]
+pub struct DescriptionTemplateError {
+    template: String,
+    placeholder: String,
+}
+
+/// Roblox needs every asset to have a name, so blank names, like ones made
+/// from a file name that's only an extension, are rejected before uploading.
+fn check_name(path: &Path, name: &str) -> Result<(), UploadImageError> {
+    if name.trim().is_empty() {
+        return Err(UploadImageError::EmptyName {
+            path: path.to_owned(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Decodes the contents of the image file at the given path, rasterizing it
+/// first if it's an SVG.
+pub fn decode_image(
+    path: &Path,
+    data: &[u8],
+    svg_size: Option<u32>,
+) -> Result<DynamicImage, UploadImageError> {
+    if UploadKind::from_path(path) == Some(UploadKind::VectorImage) {
+        rasterize_svg(data, svg_size).map_err(|source| UploadImageError::Rasterize {
+            path: path.to_owned(),
+            source,
+        })
+    } else {
+        // Most formats can be recognized from their contents, but some, like
+        // TGA, can only be recognized from their extension.
+        let format = image::guess_format(data)
+            .or_else(|_| ImageFormat::from_path(path))
+            .map_err(|_| UploadImageError::UnknownImageFormat {
+                path: path.to_owned(),
+            })?;
+
+        image::load_from_memory_with_format(data, format).map_err(|source| {
+            UploadImageError::Decode {
+                path: path.to_owned(),
+                format: format!("{:?}", format).to_uppercase(),
+                source,
+            }
+        })
+    }
+}
+
+/// Encodes an image as a PNG, which is the format that images are uploaded
+/// to Roblox in regardless of the format they started in.
+///
+/// 8-bit grayscale and RGB images are encoded as they are. Anything else, like
+/// 16-bit or BGR images, is converted to 8-bit RGB or RGBA first, since their
+/// raw bytes aren't laid out the way the PNG encoder expects.
+pub fn encode_png(img: &DynamicImage) -> ImageResult<Vec<u8>> {
+    encode_png_with_quality(img, PngCompression::Fast, PngFilter::Sub)
+}
+
+/// Bleeds the colors of an image into its transparent pixels, then encodes it
+/// as a PNG. This is how images are prepared for Roblox unless they're
+/// uploaded with options that change it.
+pub fn bleed_and_encode_png(img: &mut DynamicImage, bleed: &BleedOptions) -> ImageResult<Vec<u8>> {
+    alpha_bleed(img, bleed);
+    encode_png(img)
+}
+
+/// Encodes an image with the compression and filter it's being uploaded with,
+/// and makes sure that the result is small enough for Roblox to accept. Images
+/// that are too large are compressed harder first if `--recompress` was given.
+fn encode_for_upload(
+    img: &DynamicImage,
+    settings: &ImageUploadSettings,
+) -> Result<Vec<u8>, UploadImageError> {
+    let encode = |compression| {
+        encode_png_with_quality(img, compression, settings.png_filter)
+            .map_err(|source| UploadImageError::Encode { source })
+    };
+
+    let mut encoded = encode(settings.png_compression)?;
+
+    if encoded.len() > settings.max_file_size
+        && settings.recompress
+        && settings.png_compression != PngCompression::Best
+    {
+        log::info!(
+            "Encoded image is {} bytes, recompressing it with the best compression",
+            encoded.len()
+        );
+        encoded = encode(PngCompression::Best)?;
+    }
+
+    if encoded.len() > settings.max_file_size {
+        return Err(UploadImageError::FileTooLarge {
+            size: encoded.len(),
+            max_file_size: settings.max_file_size,
+        });
+    }
+
+    Ok(encoded)
+}
+
+/// Encodes an image as a PNG like `encode_png`, with the given compression
+/// level and filter.
+///
+/// The PNG only has the chunks that describe its pixels. Color profiles,
+/// gamma, and text from the image's original file aren't carried over, since
+/// Roblox may render images that have them differently, and leaving them out
+/// means that the same pixels are always encoded the same way.
+pub fn encode_png_with_quality(
+    img: &DynamicImage,
+    compression: PngCompression,
+    filter: PngFilter,
+) -> ImageResult<Vec<u8>> {
+    let compression = match compression {
+        PngCompression::Fast => CompressionType::Fast,
+        PngCompression::Default => CompressionType::Default,
+        PngCompression::Best => CompressionType::Best,
+    };
+
+    let filter = match filter {
+        PngFilter::None => PngFilterType::NoFilter,
+        PngFilter::Sub => PngFilterType::Sub,
+        PngFilter::Up => PngFilterType::Up,
+        PngFilter::Avg => PngFilterType::Avg,
+        PngFilter::Paeth => PngFilterType::Paeth,
     };
 
-    let response = client
-        .upload_image(upload_data)
-        .expect("Roblox API request failed");
+    let converted;
+    let img = match img.color() {
+        ColorType::L8 | ColorType::La8 | ColorType::Rgb8 | ColorType::Rgba8 => img,
+        color if color.has_alpha() => {
+            converted = DynamicImage::ImageRgba8(img.to_rgba8());
+            &converted
+        }
+        _ => {
+            converted = DynamicImage::ImageRgb8(img.to_rgb8());
+            &converted
+        }
+    };
+
+    let (width, height) = img.dimensions();
+
+    let mut encoded_image: Vec<u8> = Vec::new();
+    PngEncoder::new_with_quality(&mut encoded_image, compression, filter).encode(
+        img.as_bytes(),
+        width,
+        height,
+        img.color(),
+    )?;
+
+    Ok(encoded_image)
+}
+
+/// Shrinks an image so that neither of its sides is larger than `max_size`,
+/// preserving its aspect ratio.
+fn downscale_to_fit(img: &DynamicImage, max_size: u32) -> DynamicImage {
+    img.resize(max_size, max_size, FilterType::Lanczos3)
+}
+
+#[derive(Debug, Error)]
+pub enum UploadImageError {
+    #[error(
+        "Tarmac does not know how to upload {}. Supported file types are \
+         .png, .jpg, .tga, .bmp, .webp, .svg, .mp3, and .ogg",
+        .path.display()
+    )]
+    UnsupportedFileType { path: PathBuf },
+
+    #[error("Invalid glob pattern {pattern:?}")]
+    InvalidGlob {
+        pattern: String,
+        source: glob::Error,
+    },
+
+    #[error("No images matched the pattern {pattern:?}")]
+    NoMatches { pattern: String },
+
+    #[error(
+        "--name can't be used with the pattern {pattern:?}, since every image it matches \
+         would be given the same name"
+    )]
+    NameWithGlob { pattern: String },
+
+    #[error(transparent)]
+    DescriptionTemplate {
+        #[from]
+        source: DescriptionTemplateError,
+    },
+
+    #[error("No image data was given on stdin")]
+    EmptyStdin,
+
+    #[error("Can't upload {} without a name. Pass one with --name.", .path.display())]
+    EmptyName { path: PathBuf },
+
+    #[error("Tarmac cannot upload models yet, so {} was not uploaded", .path.display())]
+    ModelsUnsupported { path: PathBuf },
+
+    #[error(
+        "Tarmac cannot decode AVIF or HEIC images yet, so {} was not uploaded. \
+         Convert it to PNG first.",
+        .path.display()
+    )]
+    ImageFormatUnsupported { path: PathBuf },
+
+    #[error(
+        "Image is {width}x{height}, which is larger than the maximum size of {max_size}x{max_size}. \
+         Pass --resize to downscale it automatically."
+    )]
+    TooLarge {
+        width: u32,
+        height: u32,
+        max_size: u32,
+    },
+
+    #[error(
+        "Encoded image is {size} bytes, which is larger than the maximum file size of \
+         {max_file_size} bytes. Pass --recompress to compress it harder, or --max-size with \
+         --resize to make it smaller."
+    )]
+    FileTooLarge { size: usize, max_file_size: usize },
+
+    #[error(
+        "{} was uploaded as image {asset_id}, but Roblox stored an image with different pixels",
+        .path.display()
+    )]
+    VerifyMismatch { path: PathBuf, asset_id: u64 },
+
+    #[error("Couldn't decode {format} image {}", .path.display())]
+    Decode {
+        path: PathBuf,
+        format: String,
+        source: ImageError,
+    },
+
+    #[error("Couldn't tell what format the image {} is in", .path.display())]
+    UnknownImageFormat { path: PathBuf },
+
+    #[error("Couldn't rasterize SVG {}", .path.display())]
+    Rasterize { path: PathBuf, source: SvgError },
+
+    #[error("Couldn't encode image as PNG")]
+    Encode { source: ImageError },
+
+    #[error("Couldn't write processed image to {}", .path.display())]
+    WriteOutput { path: PathBuf, source: io::Error },
+
+    #[error(transparent)]
+    UploadLock {
+        #[from]
+        source: UploadLockError,
+    },
+
+    #[error(transparent)]
+    WalkDir {
+        #[from]
+        source: walkdir::Error,
+    },
+
+    #[error(transparent)]
+    UploadBatch {
+        #[from]
+        source: Box<UploadDirError>,
+    },
+
+    #[error(transparent)]
+    UploadList {
+        #[from]
+        source: Box<UploadListError>,
+    },
+
+    #[error(transparent)]
+    UploadAudio {
+        #[from]
+        source: UploadAudioError,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{options::ImageAssetType, roblox_web_api::MockUploader};
+
+    #[test]
+    fn kind_from_extension() {
+        assert_eq!(
+            UploadKind::from_path(Path::new("foo.png")),
+            Some(UploadKind::Image)
+        );
+        assert_eq!(
+            UploadKind::from_path(Path::new("foo/bar.JPG")),
+            Some(UploadKind::Image)
+        );
+        assert_eq!(
+            UploadKind::from_path(Path::new("icon.svg")),
+            Some(UploadKind::VectorImage)
+        );
+        assert_eq!(
+            UploadKind::from_path(Path::new("theme.ogg")),
+            Some(UploadKind::Audio)
+        );
+        assert_eq!(
+            UploadKind::from_path(Path::new("tree.fbx")),
+            Some(UploadKind::Model)
+        );
+        assert_eq!(
+            UploadKind::from_path(Path::new("photo.HEIC")),
+            Some(UploadKind::UndecodableImage)
+        );
+    }
+
+    #[test]
+    fn reject_name_with_glob() {
+        use structopt::StructOpt;
+
+        use crate::options::{Options, Subcommand};
+
+        let options =
+            Options::from_iter(&["tarmac", "upload-image", "icons/*.png", "--name", "Foo"]);
+        let upload_options = match options.command {
+            Subcommand::UploadImage(upload_options) => upload_options,
+            _ => unreachable!(),
+        };
+
+        assert!(matches!(
+            upload_image(options.global, upload_options),
+            Err(UploadImageError::NameWithGlob { .. })
+        ));
+    }
+
+    #[test]
+    fn undecodable_formats_are_named() {
+        let path = std::env::temp_dir().join("tarmac-undecodable_formats_are_named.avif");
+        fs::write(&path, b"").unwrap();
+
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "photo", &settings(), false);
+
+        assert!(matches!(
+            outcome,
+            Err(UploadImageError::ImageFormatUnsupported { .. })
+        ));
+        assert!(!is_image_path(&path));
+    }
+
+    #[test]
+    fn kind_from_unknown_extension() {
+        assert_eq!(UploadKind::from_path(Path::new("notes.txt")), None);
+        assert_eq!(UploadKind::from_path(Path::new("no-extension")), None);
+    }
+
+    #[test]
+    fn blank_names_rejected() {
+        let path = Path::new("icons/save.png");
+
+        assert!(check_name(path, "save").is_ok());
+        assert!(matches!(
+            check_name(path, ""),
+            Err(UploadImageError::EmptyName { .. })
+        ));
+        assert!(matches!(
+            check_name(path, "  "),
+            Err(UploadImageError::EmptyName { .. })
+        ));
+    }
+
+    #[test]
+    fn downscale_keeps_aspect_ratio() {
+        let img = DynamicImage::new_rgba8(2048, 512);
+        let scaled = downscale_to_fit(&img, 1024);
+
+        assert_eq!(scaled.dimensions(), (1024, 256));
+    }
+
+    fn settings() -> ImageUploadSettings {
+        ImageUploadSettings {
+            description: "Uploaded by Tarmac.".to_owned(),
+            description_template: None,
+            max_size: 1024,
+            resize: false,
+            svg_size: None,
+            force: false,
+            flatten: None,
+            premultiply_alpha: false,
+            no_alpha_bleed: false,
+            bleed: BleedOptions::default(),
+            png_compression: PngCompression::Fast,
+            png_filter: PngFilter::Sub,
+            max_file_size: 20 * 1024 * 1024,
+            recompress: false,
+            verify: false,
+            output_dir: None,
+            strict: false,
+            tags: Vec::new(),
+            name_prefix: String::new(),
+            asset_type: ImageAssetType::Decal,
+            manifest_out: None,
+            concurrency_safe: false,
+        }
+    }
+
+    /// Writes a 1x1 PNG to a temporary file unique to the calling test.
+    fn write_fixture(test_name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tarmac-{}.png", test_name));
+        let img = DynamicImage::new_rgba8(1, 1);
+
+        fs::write(&path, encode_png(&img).unwrap()).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn upload_then_skip_unchanged() {
+        let path = write_fixture("upload_then_skip_unchanged");
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        let first = upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), false);
+        let second = upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), false);
+
+        assert_eq!(first.unwrap(), UploadOutcome::Uploaded(1));
+        assert_eq!(second.unwrap(), UploadOutcome::Unchanged(1));
+        assert_eq!(uploader.uploads.len(), 1);
+        assert_eq!(uploader.uploads[0].name, "icon");
+    }
+
+    #[test]
+    fn verify_stored_image() {
+        let path = write_fixture("verify_stored_image");
+        let settings = ImageUploadSettings {
+            verify: true,
+            force: true,
+            ..settings()
+        };
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &settings, false);
+        assert_eq!(outcome.unwrap(), UploadOutcome::Uploaded(1));
+
+        uploader.corrupt_downloads = true;
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &settings, false);
+        assert!(matches!(
+            outcome,
+            Err(UploadImageError::VerifyMismatch { asset_id: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn output_paths_mirror_inputs() {
+        let output_dir = Path::new("out");
+
+        assert_eq!(
+            output_image_path(output_dir, Path::new("icons/save.jpg")),
+            Path::new("out/icons/save.png")
+        );
+        assert_eq!(
+            output_image_path(output_dir, Path::new("../shared/./logo.svg")),
+            Path::new("out/shared/logo.png")
+        );
+        assert_eq!(
+            output_image_path(output_dir, Path::new("/tmp/icon.png")),
+            Path::new("out/tmp/icon.png")
+        );
+    }
+
+    #[test]
+    fn write_processed_image() {
+        let path = write_fixture("write_processed_image");
+        let output_dir = std::env::temp_dir().join("tarmac-write_processed_image");
+        let settings = ImageUploadSettings {
+            output_dir: Some(output_dir.clone()),
+            force: true,
+            ..settings()
+        };
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        upload_image_file(&mut uploader, &lock, &path, "icon", &settings, false).unwrap();
+
+        let written = fs::read(output_image_path(&output_dir, &path)).unwrap();
+        assert_eq!(written, uploader.uploads[0].contents);
+
+        // A file in the way of the output directory only stops the upload
+        // when --strict is passed.
+        let blocked = ImageUploadSettings {
+            output_dir: Some(path.clone()),
+            ..settings
+        };
+        upload_image_file(&mut uploader, &lock, &path, "icon", &blocked, false).unwrap();
+
+        let strict = ImageUploadSettings {
+            strict: true,
+            ..blocked
+        };
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &strict, false);
+        assert!(matches!(outcome, Err(UploadImageError::WriteOutput { .. })));
+        assert_eq!(uploader.uploads.len(), 2);
+    }
+
+    #[test]
+    fn name_prefix() {
+        let path = write_fixture("name_prefix");
+        let prefixed = ImageUploadSettings {
+            name_prefix: "ui/".to_owned(),
+            force: true,
+            ..settings()
+        };
+        let unprefixed = ImageUploadSettings {
+            force: true,
+            ..settings()
+        };
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        upload_image_file(&mut uploader, &lock, &path, "save", &prefixed, false).unwrap();
+        assert_eq!(
+            lock.lock()
+                .unwrap()
+                .uploads
+                .values()
+                .next()
+                .unwrap()
+                .name_prefix,
+            "ui/"
+        );
+
+        upload_image_file(&mut uploader, &lock, &path, "save", &unprefixed, false).unwrap();
+
+        assert_eq!(uploader.uploads[0].name, "ui/save");
+        assert_eq!(uploader.uploads[1].name, "save");
+        assert_eq!(
+            lock.lock()
+                .unwrap()
+                .uploads
+                .values()
+                .next()
+                .unwrap()
+                .name_prefix,
+            ""
+        );
+    }
+
+    #[test]
+    fn retag_unchanged_image() {
+        let path = write_fixture("retag_unchanged_image");
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), false).unwrap();
+
+        // Start again from the lock as it was saved after the upload.
+        let saved = toml::to_vec(&*lock.lock().unwrap()).unwrap();
+        let lock = Mutex::new(toml::from_slice::<UploadLock>(&saved).unwrap());
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Unchanged(_))));
+        assert!(!lock.lock().unwrap().has_changes());
+
+        let tagged = ImageUploadSettings {
+            tags: vec!["ui".to_owned()],
+            ..settings()
+        };
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &tagged, false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Unchanged(_))));
+        assert_eq!(uploader.uploads.len(), 1);
+
+        let lock = lock.into_inner().unwrap();
+        assert!(lock.has_changes());
+        assert_eq!(lock.ids_tagged("ui").len(), 1);
+    }
+
+    #[test]
+    fn upload_again_with_other_name_prefix() {
+        let path = write_fixture("upload_again_with_other_name_prefix");
+        let prefixed = ImageUploadSettings {
+            name_prefix: "ui/".to_owned(),
+            ..settings()
+        };
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        let first = upload_image_file(&mut uploader, &lock, &path, "save", &prefixed, false);
+        assert!(matches!(first, Ok(UploadOutcome::Uploaded(_))));
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "save", &prefixed, false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Unchanged(_))));
+        assert_eq!(uploader.uploads.len(), 1);
+
+        // The asset on Roblox is named with the prefix, so leaving the prefix
+        // out makes a new asset instead of reusing it.
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "save", &settings(), false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Uploaded(_))));
+        assert_eq!(uploader.uploads[1].name, "save");
+
+        let lock = lock.into_inner().unwrap();
+        assert_eq!(lock.uploads.values().next().unwrap().name_prefix, "");
+    }
+
+    #[test]
+    fn upload_again_as_other_asset_type() {
+        let path = write_fixture("upload_again_as_other_asset_type");
+        let image = ImageUploadSettings {
+            asset_type: ImageAssetType::Image,
+            ..settings()
+        };
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        let decal = upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), false);
+        assert!(matches!(decal, Ok(UploadOutcome::Uploaded(_))));
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &image, false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Uploaded(_))));
+        assert_eq!(uploader.uploads.len(), 2);
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &image, false);
+        assert!(matches!(outcome, Ok(UploadOutcome::Unchanged(_))));
+        assert_eq!(uploader.uploads.len(), 2);
+    }
+
+    #[test]
+    fn dry_run_uploads_nothing() {
+        let path = write_fixture("dry_run_uploads_nothing");
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+
+        let outcome = upload_image_file(&mut uploader, &lock, &path, "icon", &settings(), true);
+
+        assert_eq!(outcome.unwrap(), UploadOutcome::DryRun);
+        assert!(uploader.uploads.is_empty());
+        assert!(lock.lock().unwrap().uploads.is_empty());
+    }
+
+    #[test]
+    fn skip_alpha_bleed() {
+        let path = std::env::temp_dir().join("tarmac-skip_alpha_bleed.png");
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(2, 1, |x, _| match x {
+            0 => image::Rgba([255, 0, 0, 255]),
+            _ => image::Rgba([0, 0, 255, 0]),
+        }));
+        fs::write(&path, encode_png(&img).unwrap()).unwrap();
+
+        let settings = ImageUploadSettings {
+            no_alpha_bleed: true,
+            force: true,
+            ..settings()
+        };
+
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+        upload_image_file(&mut uploader, &lock, &path, "pixels", &settings, false).unwrap();
+
+        let uploaded = image::load_from_memory(&uploader.uploads[0].contents).unwrap();
+        assert_eq!(uploaded.to_rgba8(), img.to_rgba8());
+    }
+
+    #[test]
+    fn file_size_limit() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(128, 128, |x, y| {
+            image::Rgba([x as u8, y as u8, (x ^ y) as u8, 255])
+        }));
+
+        let fast = encode_png_with_quality(&img, PngCompression::Fast, PngFilter::Sub).unwrap();
+        let best = encode_png_with_quality(&img, PngCompression::Best, PngFilter::Sub).unwrap();
+        assert!(best.len() < fast.len());
+
+        let settings = ImageUploadSettings {
+            max_file_size: best.len(),
+            ..settings()
+        };
+
+        let error = encode_for_upload(&img, &settings).unwrap_err();
+        assert!(matches!(
+            error,
+            UploadImageError::FileTooLarge { size, max_file_size }
+                if size == fast.len() && max_file_size == best.len()
+        ));
+
+        let settings = ImageUploadSettings {
+            recompress: true,
+            ..settings
+        };
+
+        assert_eq!(encode_for_upload(&img, &settings).unwrap(), best);
+
+        let settings = ImageUploadSettings {
+            max_file_size: best.len() - 1,
+            ..settings
+        };
+
+        assert!(matches!(
+            encode_for_upload(&img, &settings),
+            Err(UploadImageError::FileTooLarge { .. })
+        ));
+    }
+
+    /// Lists the type of every chunk in a PNG, in order.
+    fn png_chunk_types(png: &[u8]) -> Vec<String> {
+        let mut types = Vec::new();
+        let mut rest = &png[8..];
+
+        while rest.len() >= 12 {
+            let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            types.push(String::from_utf8_lossy(&rest[4..8]).into_owned());
+            rest = &rest[12 + length..];
+        }
+
+        types
+    }
+
+    /// Adds a chunk to a PNG right after its header.
+    fn insert_png_chunk(png: &[u8], chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut crc = flate2::Crc::new();
+        crc.update(chunk_type);
+        crc.update(data);
+
+        let mut chunk = Vec::new();
+        chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend_from_slice(data);
+        chunk.extend_from_slice(&crc.sum().to_be_bytes());
+
+        // The signature is 8 bytes and the IHDR chunk is 25.
+        let mut with_chunk = png[..33].to_vec();
+        with_chunk.extend_from_slice(&chunk);
+        with_chunk.extend_from_slice(&png[33..]);
+        with_chunk
+    }
+
+    #[test]
+    fn strip_png_metadata() {
+        let path = std::env::temp_dir().join("tarmac-strip_png_metadata.png");
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            4,
+            4,
+            image::Rgba([10, 20, 30, 255]),
+        ));
+
+        let png = encode_png(&img).unwrap();
+        let png = insert_png_chunk(&png, b"gAMA", &45455u32.to_be_bytes());
+        let png = insert_png_chunk(&png, b"tEXt", b"Comment\0made by hand");
+        assert!(png_chunk_types(&png).contains(&"gAMA".to_owned()));
+        fs::write(&path, &png).unwrap();
+
+        let settings = ImageUploadSettings {
+            force: true,
+            ..settings()
+        };
+
+        let lock = Mutex::new(UploadLock::default());
+        let mut uploader = MockUploader::default();
+        upload_image_file(&mut uploader, &lock, &path, "pixels", &settings, false).unwrap();
+
+        let uploaded = &uploader.uploads[0].contents;
+        let mut types = png_chunk_types(uploaded);
+        types.dedup();
+        assert_eq!(types, vec!["IHDR", "IDAT", "IEND"]);
+
+        assert_eq!(uploaded, &encode_png(&img).unwrap());
+    }
+
+    /// Encodes an image, then decodes it again as 8-bit RGBA.
+    fn round_trip(img: &DynamicImage) -> image::RgbaImage {
+        let encoded = encode_png(img).unwrap();
+        let decoded = image::load_from_memory(&encoded).unwrap();
+
+        assert_eq!(decoded.dimensions(), img.dimensions());
+        decoded.to_rgba8()
+    }
+
+    #[test]
+    fn encode_color_types() {
+        let rgba = image::RgbaImage::from_fn(3, 2, |x, y| {
+            image::Rgba([(x * 80) as u8, (y * 120) as u8, 200, (x * 100) as u8])
+        });
+        let rgba = DynamicImage::ImageRgba8(rgba);
+
+        let rgb = DynamicImage::ImageRgb8(rgba.to_rgb8());
+        let luma = DynamicImage::ImageLuma8(rgba.to_luma8());
+        let luma_alpha = DynamicImage::ImageLumaA8(rgba.to_luma_alpha8());
+        let bgra = DynamicImage::ImageBgra8(rgba.to_bgra8());
+        let rgba16 = DynamicImage::ImageRgba16(rgba.to_rgba16());
+
+        assert_eq!(round_trip(&rgba), rgba.to_rgba8());
+        assert_eq!(round_trip(&rgb), rgb.to_rgba8());
+        assert_eq!(round_trip(&luma), luma.to_rgba8());
+        assert_eq!(round_trip(&luma_alpha), luma_alpha.to_rgba8());
+        assert_eq!(round_trip(&bgra), rgba.to_rgba8());
+        assert_eq!(round_trip(&rgba16), rgba.to_rgba8());
+    }
+
+    #[test]
+    fn png_quality_keeps_pixels() {
+        let img = DynamicImage::ImageRgba8(image::RgbaImage::from_fn(16, 16, |x, y| {
+            image::Rgba([(x * 16) as u8, (y * 16) as u8, 0, 255])
+        }));
+
+        let fast = encode_png(&img).unwrap();
+        let best = encode_png_with_quality(&img, PngCompression::Best, PngFilter::Paeth).unwrap();
+
+        assert_eq!(
+            image::load_from_memory(&fast).unwrap().to_rgba8(),
+            image::load_from_memory(&best).unwrap().to_rgba8()
+        );
+    }
+
+    #[test]
+    fn description_template() {
+        let path = Path::new("icons/save.png");
+
+        assert_eq!(
+            render_description_template("Icon {name} from {path}", "save", path).unwrap(),
+            "Icon save from icons/save.png"
+        );
+        assert_eq!(
+            render_description_template("No placeholders", "save", path).unwrap(),
+            "No placeholders"
+        );
+
+        assert!(render_description_template("Icon {nmae}", "save", path).is_err());
+        assert!(render_description_template("Icon {name", "save", path).is_err());
+        assert!(render_description_template("Icon {}", "save", path).is_err());
+    }
+
+    /// A 2x1 32-bit TGA image, with an opaque red pixel and a half transparent
+    /// blue pixel.
+    const TGA_FIXTURE: &str = "AAACAAAAAAAAAAAAAgABACAoAAD///8AAIA=";
+
+    /// A 2x1 24-bit BMP image, with a red pixel and a green pixel.
+    const BMP_FIXTURE: &str =
+        "Qk0+AAAAAAAAADYAAAAoAAAAAgAAAAEAAAABABgAAAAAAAgAAAATCwAAEwsAAAAAAAAAAAAAAAD/AP8AAAA=";
+
+    #[test]
+    fn decode_tga() {
+        let tga = base64::decode(TGA_FIXTURE).unwrap();
+        let img = decode_image(Path::new("icon.tga"), &tga, None).unwrap();
+
+        let pixels = round_trip(&img);
+        assert_eq!(pixels.get_pixel(0, 0), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(pixels.get_pixel(1, 0), &image::Rgba([0, 0, 255, 128]));
+    }
+
+    #[test]
+    fn decode_bmp() {
+        let bmp = base64::decode(BMP_FIXTURE).unwrap();
+        let img = decode_image(Path::new("icon.bmp"), &bmp, None).unwrap();
+
+        let pixels = round_trip(&img);
+        assert_eq!(pixels.get_pixel(0, 0), &image::Rgba([255, 0, 0, 255]));
+        assert_eq!(pixels.get_pixel(1, 0), &image::Rgba([0, 255, 0, 255]));
+    }
+
+    #[test]
+    fn decode_errors_name_format() {
+        let error = decode_image(Path::new("broken.bmp"), b"BM not really", None).unwrap_err();
+        assert_eq!(error.to_string(), "Couldn't decode BMP image broken.bmp");
+
+        let error = decode_image(Path::new("mystery.tga"), b"", None).unwrap_err();
+        assert!(matches!(error, UploadImageError::Decode { .. }));
+
+        let error = decode_image(Path::new("<stdin>"), b"nothing", None).unwrap_err();
+        assert!(matches!(error, UploadImageError::UnknownImageFormat { .. }));
+    }
+
+    /// A 1x1 lossy WebP image.
+    const WEBP_FIXTURE: &str = "UklGRiIAAABXRUJQVlA4IBYAAAAwAQCdASoBAAEADsD+JaQAA3AAAAAA";
+
+    #[test]
+    fn webp_round_trips_to_png() {
+        let webp = base64::decode(WEBP_FIXTURE).unwrap();
+        let img = image::load_from_memory(&webp).unwrap();
+
+        let encoded = encode_png(&img).unwrap();
 
-    eprintln!("Image uploaded successfully!");
-    println!("{}", response.backing_asset_id);
+        assert_eq!(
+            image::guess_format(&encoded).unwrap(),
+            image::ImageFormat::Png
+        );
+        assert_eq!(
+            image::load_from_memory(&encoded).unwrap().dimensions(),
+            (1, 1)
+        );
+    }
 }
@@ -0,0 +1,534 @@
+use fs_err as fs;
+use thiserror::Error;
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{
+    commands::{
+        asset_name_for, content_hash, error_chain, print_json, upload_image_file, UploadImageError,
+        UploadOutcome, UploadRecord,
+    },
+    data::{UploadLock, UploadLockError, UploadLockGuard},
+    exit_code::{Classify, FailureKind},
+    options::{GlobalOptions, HexColor, ImageAssetType, ImageUploadSettings, OutputFormat},
+    roblox_web_api::{RobloxApiClient, RobloxApiError},
+};
+
+/// One image listed in a file given to `upload-image --from-file`, written
+/// like `icons/save.png | Save | The save icon | 123 | asset-type=image`.
+/// Everything but the path is optional, and empty fields are the same as
+/// leaving them out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListEntry {
+    path: PathBuf,
+    name: Option<String>,
+    description: Option<String>,
+
+    /// An asset that the image has already been uploaded to, which is used
+    /// instead of uploading it again.
+    asset_id: Option<u64>,
+
+    overrides: Overrides,
+}
+
+impl ListEntry {
+    /// The settings to upload this entry's image with. Anything given in the
+    /// entry takes precedence over the settings from the command line.
+    fn settings(&self, settings: &ImageUploadSettings) -> ImageUploadSettings {
+        let mut settings = self.overrides.apply(settings);
+
+        if let Some(description) = &self.description {
+            settings.description = description.clone();
+            settings.description_template = None;
+        }
+
+        settings
+    }
+}
+
+/// Settings given for a single entry, written like the options they replace
+/// but without the dashes in front and separated by commas, like
+/// `asset-type=image, no-alpha-bleed`. Flags can be given a value of `true`
+/// or `false`, so that an entry can turn off a flag given on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Overrides {
+    asset_type: Option<ImageAssetType>,
+    max_size: Option<u32>,
+    resize: Option<bool>,
+    force: Option<bool>,
+    no_alpha_bleed: Option<bool>,
+    flatten: Option<HexColor>,
+    premultiply_alpha: Option<bool>,
+}
+
+impl Overrides {
+    fn apply(&self, settings: &ImageUploadSettings) -> ImageUploadSettings {
+        let mut settings = settings.clone();
+
+        if let Some(asset_type) = self.asset_type {
+            settings.asset_type = asset_type;
+        }
+
+        if let Some(max_size) = self.max_size {
+            settings.max_size = max_size;
+        }
+
+        if let Some(resize) = self.resize {
+            settings.resize = resize;
+        }
+
+        if let Some(force) = self.force {
+            settings.force = force;
+        }
+
+        if let Some(no_alpha_bleed) = self.no_alpha_bleed {
+            settings.no_alpha_bleed = no_alpha_bleed;
+        }
+
+        // An image can't be both flattened and premultiplied, so choosing one
+        // for an entry turns off the other from the command line.
+        if let Some(color) = self.flatten {
+            settings.flatten = Some(color);
+            settings.premultiply_alpha = false;
+        }
+
+        if let Some(premultiply_alpha) = self.premultiply_alpha {
+            settings.premultiply_alpha = premultiply_alpha;
+
+            if premultiply_alpha {
+                settings.flatten = None;
+            }
+        }
+
+        settings
+    }
+}
+
+/// Uploads every image listed in the given file, one after another, using the
+/// same client. Entries that are malformed or fail to upload are reported with
+/// their line number and don't stop the rest unless `fail_fast` is set.
+pub fn upload_image_list(
+    global: &GlobalOptions,
+    list_path: &Path,
+    settings: &ImageUploadSettings,
+    fail_fast: bool,
+) -> Result<(), UploadListError> {
+    let contents = fs::read_to_string(list_path)?;
+    let entries = parse_list(&contents);
+
+    let mut client = RobloxApiClient::from_options(global)?;
+
+    client.require_auth()?;
+
+    // Held until we're done with the lock file, even if uploads fail.
+    let _guard = if settings.concurrency_safe {
+        Some(UploadLockGuard::acquire(".")?)
+    } else {
+        None
+    };
+
+    let lock = Mutex::new(UploadLock::read_from_folder(".")?);
+
+    let mut records = Vec::new();
+    let mut succeeded = Vec::new();
+    let mut failed = Vec::new();
+    let mut uploaded = 0;
+    let mut unchanged = 0;
+    let mut dry_run = 0;
+
+    for (line, entry) in entries {
+        let result = entry
+            .map_err(|reason| UploadListError::Malformed {
+                path: list_path.to_owned(),
+                line,
+                reason,
+            })
+            .and_then(|entry| {
+                let outcome = upload_entry(&mut client, &lock, &entry, settings, global.dry_run)
+                    .map_err(|source| UploadListError::Upload {
+                        path: list_path.to_owned(),
+                        line,
+                        source: Box::new(source),
+                    })?;
+
+                Ok((entry.path, outcome))
+            });
+
+        match result {
+            Ok((path, outcome)) => {
+                match outcome {
+                    UploadOutcome::Uploaded(_) => {
+                        // Save after every upload so that progress isn't lost
+                        // if a later upload fails.
+                        lock.lock().unwrap().write_to_folder(".")?;
+                        uploaded += 1;
+                    }
+                    UploadOutcome::Unchanged(_) => unchanged += 1,
+                    UploadOutcome::DryRun => dry_run += 1,
+                }
+
+                match global.output_format {
+                    OutputFormat::Text => println!("{}\t{}", path.display(), outcome),
+                    OutputFormat::Json => records.push(UploadRecord::succeeded(&path, outcome)),
+                }
+
+                succeeded.push(path);
+            }
+            Err(error) => {
+                if fail_fast {
+                    if global.output_format == OutputFormat::Json {
+                        print_json(&records);
+                    }
+
+                    return Err(error);
+                }
+
+                failed.push(error);
+            }
+        }
+    }
+
+    let lock = lock.into_inner().unwrap();
+
+    // Images given an existing asset ID are recorded without being uploaded,
+    // so the lock file needs saving for them too.
+    if !global.dry_run {
+        lock.write_to_folder(".")?;
+
+        if let Some(manifest_path) = &settings.manifest_out {
+            lock.write_manifest(succeeded.iter().map(PathBuf::as_path), manifest_path)?;
+        }
+    }
+
+    if global.output_format == OutputFormat::Json {
+        print_json(&records);
+    }
+
+    if !global.quiet {
+        if dry_run > 0 {
+            eprintln!("Dry run: {} image(s) would have been uploaded.", dry_run);
+        }
+
+        eprintln!(
+            "Uploaded {} image(s), skipped {} unchanged image(s), {} failed.",
+            uploaded,
+            unchanged,
+            failed.len()
+        );
+    }
+
+    for error in &failed {
+        eprintln!("  {}", error_chain(error));
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(UploadListError::HadErrors {
+            error_count: failed.len(),
+            failure_kind: FailureKind::common(failed.iter().map(Classify::failure_kind)),
+        })
+    }
+}
+
+/// Uploads the image of one entry, or records the asset it was given without
+/// uploading anything.
+fn upload_entry(
+    client: &mut RobloxApiClient,
+    lock: &Mutex<UploadLock>,
+    entry: &ListEntry,
+    settings: &ImageUploadSettings,
+    dry_run: bool,
+) -> Result<UploadOutcome, UploadImageError> {
+    let name = entry
+        .name
+        .clone()
+        .unwrap_or_else(|| asset_name_for(&entry.path));
+
+    let settings = entry.settings(settings);
+
+    if let (Some(id), false) = (entry.asset_id, settings.force) {
+        let hash = content_hash(&fs::read(&entry.path)?);
+
+        let mut lock = lock.lock().unwrap();
+        lock.insert(&entry.path, hash, id, settings.asset_type);
+        lock.set_tags(&entry.path, &settings.tags);
+        lock.set_name_prefix(&entry.path, &settings.name_prefix);
+
+        return Ok(UploadOutcome::Unchanged(id));
+    }
+
+    upload_image_file(client, lock, &entry.path, &name, &settings, dry_run)
+}
+
+/// Parses a list of images, one per line. Blank lines and lines starting with
+/// `#` are skipped. Each entry is paired with its line number, starting at 1.
+fn parse_list(contents: &str) -> Vec<(usize, Result<ListEntry, String>)> {
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_number, line)| (line_number, parse_entry(line)))
+        .collect()
+}
+
+fn parse_entry(line: &str) -> Result<ListEntry, String> {
+    let fields: Vec<&str> = line.split('|').map(str::trim).collect();
+
+    if fields.len() > 5 {
+        return Err(format!(
+            "Expected at most 5 fields separated by |, but found {}",
+            fields.len()
+        ));
+    }
+
+    let field = |index: usize| {
+        fields
+            .get(index)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.to_string())
+    };
+
+    let path = field(0).ok_or_else(|| String::from("Expected a path"))?;
+
+    let asset_id = match field(3) {
+        Some(id) => match id.parse() {
+            Ok(id) if id > 0 => Some(id),
+            _ => return Err(format!("Invalid asset ID '{}'", id)),
+        },
+        None => None,
+    };
+
+    let overrides = match field(4) {
+        Some(overrides) => parse_overrides(&overrides)?,
+        None => Overrides::default(),
+    };
+
+    Ok(ListEntry {
+        path: PathBuf::from(path),
+        name: field(1),
+        description: field(2),
+        asset_id,
+        overrides,
+    })
+}
+
+fn parse_overrides(field: &str) -> Result<Overrides, String> {
+    let mut overrides = Overrides::default();
+
+    for setting in field.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (key, value) = match setting.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (setting, None),
+        };
+
+        let required = || value.ok_or_else(|| format!("Expected a value for '{}'", key));
+
+        match key {
+            "asset-type" => overrides.asset_type = Some(required()?.parse()?),
+            "max-size" => {
+                let value = required()?;
+                let max_size = value
+                    .parse()
+                    .map_err(|_| format!("Invalid max-size '{}'", value))?;
+
+                overrides.max_size = Some(max_size);
+            }
+            "resize" => overrides.resize = Some(parse_flag(key, value)?),
+            "force" => overrides.force = Some(parse_flag(key, value)?),
+            "no-alpha-bleed" => overrides.no_alpha_bleed = Some(parse_flag(key, value)?),
+            "flatten" => overrides.flatten = Some(required()?.parse()?),
+            "premultiply-alpha" => overrides.premultiply_alpha = Some(parse_flag(key, value)?),
+            _ => return Err(format!("Unknown setting '{}'", key)),
+        }
+    }
+
+    if overrides.flatten.is_some() && overrides.premultiply_alpha == Some(true) {
+        return Err(String::from(
+            "flatten and premultiply-alpha can't be used together",
+        ));
+    }
+
+    Ok(overrides)
+}
+
+/// Flags are turned on by giving them without a value.
+fn parse_flag(key: &str, value: Option<&str>) -> Result<bool, String> {
+    match value {
+        None | Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(value) => Err(format!(
+            "Invalid value '{}' for '{}'. Valid values are true and false.",
+            value, key
+        )),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UploadListError {
+    #[error("Upload completed, but with {error_count} error(s)")]
+    HadErrors {
+        error_count: usize,
+
+        /// The kind of failure that every error was, if they were all the
+        /// same kind.
+        failure_kind: FailureKind,
+    },
+
+    #[error("Line {line} of {} is malformed: {reason}", .path.display())]
+    Malformed {
+        path: PathBuf,
+        line: usize,
+        reason: String,
+    },
+
+    #[error("Couldn't upload the image on line {line} of {}", .path.display())]
+    Upload {
+        path: PathBuf,
+        line: usize,
+        source: Box<UploadImageError>,
+    },
+
+    #[error(transparent)]
+    UploadLock {
+        #[from]
+        source: UploadLockError,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_entries() {
+        let entries = parse_list(
+            "# Icons\n\
+             icons/save.png | Save | The save icon | 123\n\
+             \n\
+             icons/load.png\n\
+             icons/open.png | | | 0\n\
+             icons/close.png | Close || \n\
+             | Nothing\n",
+        );
+
+        assert_eq!(
+            entries[0],
+            (
+                2,
+                Ok(ListEntry {
+                    path: PathBuf::from("icons/save.png"),
+                    name: Some("Save".to_owned()),
+                    description: Some("The save icon".to_owned()),
+                    asset_id: Some(123),
+                    overrides: Overrides::default(),
+                })
+            )
+        );
+        assert_eq!(
+            entries[1],
+            (
+                4,
+                Ok(ListEntry {
+                    path: PathBuf::from("icons/load.png"),
+                    name: None,
+                    description: None,
+                    asset_id: None,
+                    overrides: Overrides::default(),
+                })
+            )
+        );
+
+        assert_eq!(entries[2].0, 5);
+        assert!(entries[2].1.is_err());
+        assert_eq!(
+            entries[3].1.as_ref().unwrap().name.as_deref(),
+            Some("Close")
+        );
+        assert!(entries[4].1.is_err());
+        assert!(parse_entry("a.png | b | c | 1 | resize | d").is_err());
+    }
+
+    #[test]
+    fn parse_entry_overrides() {
+        let entry = parse_entry(
+            "a.png | | | | asset-type=image, max-size=512, no-alpha-bleed, force=false, flatten=#ffffff",
+        )
+        .unwrap();
+
+        assert_eq!(
+            entry.overrides,
+            Overrides {
+                asset_type: Some(ImageAssetType::Image),
+                max_size: Some(512),
+                no_alpha_bleed: Some(true),
+                force: Some(false),
+                flatten: Some(HexColor([255, 255, 255])),
+                ..Overrides::default()
+            }
+        );
+
+        assert!(parse_entry("a.png | | | | asset-type=model").is_err());
+        assert!(parse_entry("a.png | | | | asset-type").is_err());
+        assert!(parse_entry("a.png | | | | resize=yes").is_err());
+        assert!(parse_entry("a.png | | | | colour=red").is_err());
+        assert!(parse_entry("a.png | | | | flatten=#000000, premultiply-alpha").is_err());
+    }
+
+    #[test]
+    fn entry_settings_take_precedence() {
+        use structopt::StructOpt;
+
+        use crate::options::UploadImageOptions;
+
+        let global = UploadImageOptions::from_iter(&[
+            "upload-image",
+            "--from-file",
+            "images.txt",
+            "--asset-type",
+            "image",
+            "--max-size",
+            "256",
+            "--force",
+            "--premultiply-alpha",
+            "--description",
+            "Icons",
+        ])
+        .settings;
+
+        // Entries without overrides use the settings from the command line.
+        let plain = parse_entry("a.png").unwrap().settings(&global);
+        assert_eq!(plain.asset_type, ImageAssetType::Image);
+        assert_eq!(plain.max_size, 256);
+        assert!(plain.force);
+        assert_eq!(plain.description, "Icons");
+
+        let overridden =
+            parse_entry("a.png | | Save icon | | asset-type=decal, force=false, flatten=#000000")
+                .unwrap()
+                .settings(&global);
+        assert_eq!(overridden.asset_type, ImageAssetType::Decal);
+        assert_eq!(overridden.max_size, 256);
+        assert!(!overridden.force);
+        assert_eq!(overridden.flatten, Some(HexColor([0, 0, 0])));
+        assert!(!overridden.premultiply_alpha);
+        assert_eq!(overridden.description, "Save icon");
+    }
+}
@@ -0,0 +1,198 @@
+use std::{error::Error, path::Path};
+
+use serde::Serialize;
+
+use crate::commands::UploadOutcome;
+
+/// Describes what happened to one file that Tarmac was asked to upload. With
+/// `--output-format json`, these are printed to stdout instead of bare asset
+/// IDs so that scripts can consume them.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadRecord {
+    pub path: String,
+    pub status: UploadStatus,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<AssetChange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UploadStatus {
+    Uploaded,
+    Unchanged,
+    DryRun,
+    Failed,
+}
+
+/// How the asset of a file changed compared to the last time it was recorded
+/// in `tarmac-lock.toml`, like `{ "type": "updated", "previousAssetId": 12 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum AssetChange {
+    /// The file had never been uploaded before.
+    New,
+
+    /// The file was uploaded to a different asset than before.
+    Updated {
+        #[serde(rename = "previousAssetId")]
+        previous_id: u64,
+    },
+
+    /// The file still has the same asset as before.
+    Unchanged,
+}
+
+impl AssetChange {
+    pub fn between(previous_id: Option<u64>, id: u64) -> Self {
+        match previous_id {
+            None => AssetChange::New,
+            Some(previous_id) if previous_id != id => AssetChange::Updated { previous_id },
+            Some(_) => AssetChange::Unchanged,
+        }
+    }
+}
+
+impl UploadRecord {
+    pub fn succeeded(path: &Path, outcome: UploadOutcome) -> Self {
+        let status = match outcome {
+            UploadOutcome::Uploaded(_) => UploadStatus::Uploaded,
+            UploadOutcome::Unchanged(_) => UploadStatus::Unchanged,
+            UploadOutcome::DryRun => UploadStatus::DryRun,
+        };
+
+        Self {
+            path: path.display().to_string(),
+            status,
+            asset_id: outcome.id(),
+            error: None,
+            change: None,
+        }
+    }
+
+    pub fn with_change(self, change: Option<AssetChange>) -> Self {
+        Self { change, ..self }
+    }
+
+    pub fn failed(path: &Path, error: &dyn Error) -> Self {
+        Self {
+            path: path.display().to_string(),
+            status: UploadStatus::Failed,
+            asset_id: None,
+            error: Some(error_chain(error)),
+            change: None,
+        }
+    }
+}
+
+/// Describes an error along with everything that caused it, on one line, like
+/// `Couldn't upload foo.png as 'foo': Roblox API returned HTTP 400 ...`.
+pub fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+
+    message
+}
+
+/// Prints a value to stdout as JSON, for `--output-format json`.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    // Everything we print only contains strings and numbers, so it can always
+    // be serialized.
+    let json = serde_json::to_string_pretty(value).expect("couldn't serialize output");
+
+    println!("{}", json);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serialize_succeeded() {
+        let record = UploadRecord::succeeded(Path::new("foo.png"), UploadOutcome::Uploaded(12));
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "path": "foo.png",
+                "status": "uploaded",
+                "assetId": 12,
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_change() {
+        let record = UploadRecord::succeeded(Path::new("foo.png"), UploadOutcome::Uploaded(12))
+            .with_change(Some(AssetChange::between(Some(7), 12)));
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "path": "foo.png",
+                "status": "uploaded",
+                "assetId": 12,
+                "change": { "type": "updated", "previousAssetId": 7 },
+            })
+        );
+
+        assert_eq!(AssetChange::between(None, 12), AssetChange::New);
+        assert_eq!(AssetChange::between(Some(12), 12), AssetChange::Unchanged);
+    }
+
+    #[test]
+    fn serialize_dry_run() {
+        let record = UploadRecord::succeeded(Path::new("foo.png"), UploadOutcome::DryRun);
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "path": "foo.png",
+                "status": "dryRun",
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_failed() {
+        let error = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        let record = UploadRecord::failed(Path::new("foo.png"), &error);
+
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "path": "foo.png",
+                "status": "failed",
+                "error": "missing",
+            })
+        );
+    }
+
+    #[test]
+    fn chain_of_errors() {
+        #[derive(Debug, thiserror::Error)]
+        #[error("Couldn't upload foo.png")]
+        struct Outer {
+            source: std::io::Error,
+        }
+
+        let error = Outer {
+            source: std::io::Error::other("HTTP 400"),
+        };
+
+        assert_eq!(error_chain(&error), "Couldn't upload foo.png: HTTP 400");
+    }
+}
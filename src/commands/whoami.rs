@@ -0,0 +1,73 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    commands::print_json,
+    options::{GlobalOptions, OutputFormat},
+    roblox_web_api::{AuthenticatedUser, GroupMembership, RobloxApiClient, RobloxApiError},
+};
+
+pub fn whoami(global: GlobalOptions) -> Result<(), WhoamiError> {
+    let mut client = RobloxApiClient::from_options(&global)?;
+
+    client.require_auth()?;
+
+    let user = match client.get_authenticated_user() {
+        Ok(user) => user,
+        Err(RobloxApiError::NotAuthenticated) => return Err(WhoamiError::NotAuthenticated),
+        Err(err) => return Err(err.into()),
+    };
+
+    let groups = client.get_user_groups(user.id)?;
+
+    match global.output_format {
+        OutputFormat::Text => print_identity(&user, &groups),
+        OutputFormat::Json => print_json(&Identity {
+            user: &user,
+            groups: &groups,
+        }),
+    }
+
+    Ok(())
+}
+
+/// Who the authentication cookie belongs to, as printed with
+/// `--output-format json`.
+#[derive(Debug, Serialize)]
+struct Identity<'a> {
+    user: &'a AuthenticatedUser,
+    groups: &'a [GroupMembership],
+}
+
+fn print_identity(user: &AuthenticatedUser, groups: &[GroupMembership]) {
+    println!("User: {} (@{}, {})", user.display_name, user.name, user.id);
+
+    if groups.is_empty() {
+        println!("Groups: none");
+        return;
+    }
+
+    println!("Groups:");
+
+    for membership in groups {
+        println!(
+            "  {} ({}), role {} (rank {})",
+            membership.group.name, membership.group.id, membership.role.name, membership.role.rank
+        );
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WhoamiError {
+    #[error(
+        "Not authenticated: Roblox did not accept the authentication cookie. \
+         It has probably expired, so log in to Roblox Studio again or pass a new cookie with --auth."
+    )]
+    NotAuthenticated,
+
+    #[error(transparent)]
+    RobloxApi {
+        #[from]
+        source: RobloxApiError,
+    },
+}
@@ -1,5 +1,5 @@
 use std::{
-    io,
+    env, io,
     path::{Path, PathBuf},
 };
 
@@ -7,9 +7,9 @@ use fs_err as fs;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::glob::Glob;
+use crate::{glob::Glob, options::RetryPolicy};
 
-static CONFIG_FILENAME: &str = "tarmac.toml";
+pub static CONFIG_FILENAME: &str = "tarmac.toml";
 
 /// Configuration for Tarmac, contained in a tarmac.toml file.
 ///
@@ -51,6 +51,12 @@ pub struct Config {
     #[serde(default)]
     pub inputs: Vec<InputConfig>,
 
+    /// Defaults for Tarmac's global command line options. These are read from
+    /// the tarmac.toml closest to the working directory, not from the project
+    /// being synced.
+    #[serde(default)]
+    pub options: OptionsConfig,
+
     /// The path that this config came from. Paths from this config should be
     /// relative to the folder containing this file.
     #[serde(skip)]
@@ -121,6 +127,61 @@ impl Config {
     }
 }
 
+/// Defaults for Tarmac's global command line options, given in the `[options]`
+/// table of a tarmac.toml file. Options given on the command line take
+/// precedence over these.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct OptionsConfig {
+    pub api_base: Option<String>,
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub retries: Option<usize>,
+    pub retry_policy: Option<RetryPolicy>,
+    pub max_retry_after: Option<u64>,
+    pub timeout: Option<u64>,
+    pub connect_timeout: Option<u64>,
+}
+
+impl OptionsConfig {
+    /// Reads the options from the closest tarmac.toml in the working directory
+    /// or any of its ancestors. If there isn't one, all options are unset.
+    pub fn load() -> Result<Self, ConfigError> {
+        let current_dir = env::current_dir()?;
+
+        for folder in current_dir.ancestors() {
+            let path = folder.join(CONFIG_FILENAME);
+
+            let contents = match fs::read(&path) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            };
+
+            let file: OptionsFile =
+                toml::from_slice(&contents).map_err(|source| ConfigError::Toml {
+                    source,
+                    path: path.clone(),
+                })?;
+
+            log::debug!("Loaded options from {}", path.display());
+
+            return Ok(file.options);
+        }
+
+        Ok(Self::default())
+    }
+}
+
+/// The parts of a tarmac.toml file that `OptionsConfig::load` cares about.
+/// Everything else is ignored so that unrelated mistakes in a project's
+/// config don't stop other commands from running.
+#[derive(Deserialize)]
+struct OptionsFile {
+    #[serde(default)]
+    options: OptionsConfig,
+}
+
 fn default_max_spritesheet_size() -> (u32, u32) {
     (1024, 1024)
 }
@@ -192,3 +253,55 @@ fn make_absolute(path: &mut PathBuf, base: &Path) {
         *path = new_path;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn options_table() {
+        let config: Config = toml::from_str(
+            r#"
+            name = "foo"
+
+            [options]
+            api-base = "sitetest1.robloxlabs.com"
+            retries = 5
+            retry-policy = "fixed:4:1000"
+            connect-timeout = 30
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.options,
+            OptionsConfig {
+                api_base: Some("sitetest1.robloxlabs.com".to_owned()),
+                retries: Some(5),
+                retry_policy: Some(RetryPolicy::Fixed {
+                    attempts: 4,
+                    delay: Duration::from_millis(1000),
+                }),
+                connect_timeout: Some(30),
+                ..OptionsConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn options_ignore_project() {
+        let file: OptionsFile = toml::from_str(
+            r#"
+            not-a-project-key = true
+
+            [options]
+            proxy = "http://localhost:8888"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.options.proxy.as_deref(), Some("http://localhost:8888"));
+    }
+}
@@ -1,7 +1,9 @@
 mod config;
 mod manifest;
 mod sync;
+mod upload_lock;
 
 pub use config::*;
 pub use manifest::*;
 pub use sync::*;
+pub use upload_lock::*;
@@ -1,9 +1,6 @@
 use std::{fmt, path::PathBuf};
 
-use crate::{
-    asset_name::AssetName,
-    data::{ImageSlice, InputConfig, InputManifest},
-};
+use crate::data::{ImageSlice, InputConfig, InputManifest};
 
 use path_slash::PathBufExt;
 
@@ -36,9 +33,6 @@ impl fmt::Display for AssetId {
 /// results of network I/O, and from the previous Tarmac manifest file.
 #[derive(Debug)]
 pub struct SyncInput {
-    /// A unique name for this asset in the project.
-    pub name: AssetName,
-
     /// The path on disk to the file this input originated from.
     pub path: PathBuf,
 
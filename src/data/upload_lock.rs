@@ -0,0 +1,502 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::TryLockError,
+    io,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::options::ImageAssetType;
+
+static UPLOAD_LOCK_FILENAME: &str = "tarmac-lock.toml";
+static UPLOAD_LOCK_GUARD_FILENAME: &str = "tarmac-lock.toml.lock";
+
+/// How long to wait for another Tarmac process to release the lock file before
+/// giving up.
+const GUARD_TIMEOUT: Duration = Duration::from_secs(120);
+const GUARD_POLL_DELAY: Duration = Duration::from_millis(250);
+
+/// Remembers the files uploaded by `upload-image` so that uploading a file
+/// that hasn't changed since it was last uploaded can reuse the existing asset
+/// instead of uploading it again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UploadLock {
+    pub uploads: BTreeMap<String, LockedUpload>,
+
+    /// Whether an upload has been recorded or changed since the lock was
+    /// read, so that it needs writing back.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl UploadLock {
+    /// Reads the lock file from the given folder, or returns an empty lock if
+    /// there isn't one yet.
+    pub fn read_from_folder<P: AsRef<Path>>(folder_path: P) -> Result<Self, UploadLockError> {
+        let file_path = &folder_path.as_ref().join(UPLOAD_LOCK_FILENAME);
+
+        let contents = match fs::read(file_path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+
+        toml::from_slice(&contents).map_err(|source| UploadLockError::DeserializeToml {
+            source,
+            file_path: file_path.to_owned(),
+        })
+    }
+
+    pub fn write_to_folder<P: AsRef<Path>>(&self, folder_path: P) -> Result<(), UploadLockError> {
+        let file_path = &folder_path.as_ref().join(UPLOAD_LOCK_FILENAME);
+
+        let serialized = toml::to_vec(self)?;
+        fs::write(file_path, serialized)?;
+
+        log::trace!("Saved upload lock to {}", file_path.display());
+
+        Ok(())
+    }
+
+    /// Returns the asset ID that the file at the given path was uploaded to, as
+    /// long as its contents still have the same hash and it was uploaded as
+    /// the same type of asset with the same name prefix.
+    pub fn get(
+        &self,
+        path: &Path,
+        hash: &str,
+        asset_type: ImageAssetType,
+        name_prefix: &str,
+    ) -> Option<u64> {
+        self.uploads
+            .get(&lock_key(path))
+            .filter(|upload| upload.hash == hash && upload.was_uploaded_as(asset_type, name_prefix))
+            .map(|upload| upload.id)
+    }
+
+    /// Returns the asset ID that the file at the given path was last uploaded
+    /// to as the given type of asset with the given name prefix, whatever its
+    /// contents were.
+    pub fn id_as(&self, path: &Path, asset_type: ImageAssetType, name_prefix: &str) -> Option<u64> {
+        self.uploads
+            .get(&lock_key(path))
+            .filter(|upload| upload.was_uploaded_as(asset_type, name_prefix))
+            .map(|upload| upload.id)
+    }
+
+    /// Returns the asset ID that the file at the given path was last uploaded
+    /// to, whatever its contents were.
+    pub fn id_of(&self, path: &Path) -> Option<u64> {
+        self.uploads.get(&lock_key(path)).map(|upload| upload.id)
+    }
+
+    /// Whether an upload has been recorded or changed since the lock was
+    /// read.
+    pub fn has_changes(&self) -> bool {
+        self.changed
+    }
+
+    pub fn insert(&mut self, path: &Path, hash: String, id: u64, asset_type: ImageAssetType) {
+        self.changed = true;
+        self.uploads.insert(
+            lock_key(path),
+            LockedUpload {
+                hash,
+                id,
+                asset_type,
+                tags: Vec::new(),
+                name_prefix: String::new(),
+            },
+        );
+    }
+
+    /// Replaces the tags of the upload of the file at the given path, if it
+    /// has been uploaded.
+    pub fn set_tags(&mut self, path: &Path, tags: &[String]) {
+        if let Some(upload) = self.uploads.get_mut(&lock_key(path)) {
+            if upload.tags != tags {
+                upload.tags = tags.to_vec();
+                self.changed = true;
+            }
+        }
+    }
+
+    /// Replaces the prefix that the name of the upload of the file at the
+    /// given path was given, if it has been uploaded.
+    pub fn set_name_prefix(&mut self, path: &Path, name_prefix: &str) {
+        if let Some(upload) = self.uploads.get_mut(&lock_key(path)) {
+            if upload.name_prefix != name_prefix {
+                upload.name_prefix = name_prefix.to_owned();
+                self.changed = true;
+            }
+        }
+    }
+
+    /// The IDs of every uploaded asset that has the given tag.
+    pub fn ids_tagged(&self, tag: &str) -> HashSet<u64> {
+        self.uploads
+            .values()
+            .filter(|upload| upload.tags.iter().any(|upload_tag| upload_tag == tag))
+            .map(|upload| upload.id)
+            .collect()
+    }
+
+    /// Writes the uploads of the given files to a JSON object keyed by path,
+    /// for tools that want to know what each file was uploaded as. Files that
+    /// haven't been uploaded are left out.
+    pub fn write_manifest<'a, I>(&self, paths: I, output_path: &Path) -> Result<(), UploadLockError>
+    where
+        I: IntoIterator<Item = &'a Path>,
+    {
+        let manifest: BTreeMap<String, &LockedUpload> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let key = lock_key(path);
+                let upload = self.uploads.get(&key)?;
+                Some((key, upload))
+            })
+            .collect();
+
+        let serialized = serde_json::to_vec_pretty(&manifest)?;
+        fs::write(output_path, serialized)?;
+
+        log::debug!("Wrote upload manifest to {}", output_path.display());
+
+        Ok(())
+    }
+}
+
+/// Keeps other Tarmac processes from reading and writing the lock file in a
+/// folder until it's dropped, by holding an exclusive OS lock on a
+/// `tarmac-lock.toml.lock` file next to it. The OS releases the lock when the
+/// file is closed, including when Tarmac crashes or is killed.
+#[derive(Debug)]
+pub struct UploadLockGuard {
+    _file: fs::File,
+}
+
+impl UploadLockGuard {
+    /// Acquires the guard for the given folder, waiting for another process to
+    /// release it first if needed.
+    pub fn acquire<P: AsRef<Path>>(folder_path: P) -> Result<Self, UploadLockError> {
+        Self::acquire_within(folder_path.as_ref(), GUARD_TIMEOUT)
+    }
+
+    fn acquire_within(folder_path: &Path, timeout: Duration) -> Result<Self, UploadLockError> {
+        let file_path = folder_path.join(UPLOAD_LOCK_GUARD_FILENAME);
+        let started = Instant::now();
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&file_path)?;
+
+        loop {
+            match file.file().try_lock() {
+                Ok(()) => {
+                    log::trace!("Acquired {}", file_path.display());
+                    return Ok(Self { _file: file });
+                }
+                Err(TryLockError::WouldBlock) => {
+                    if started.elapsed() >= timeout {
+                        return Err(UploadLockError::Held { file_path });
+                    }
+
+                    if started.elapsed() < GUARD_POLL_DELAY {
+                        log::info!(
+                            "Waiting for another Tarmac process to release {}",
+                            file_path.display()
+                        );
+                    }
+
+                    thread::sleep(GUARD_POLL_DELAY);
+                }
+                Err(TryLockError::Error(source)) => {
+                    return Err(UploadLockError::Lock { file_path, source });
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedUpload {
+    /// The hexadecimal encoded hash of the contents of the file when it was
+    /// uploaded.
+    pub hash: String,
+
+    /// The asset ID that the file was uploaded to.
+    pub id: u64,
+
+    /// The type of asset that the file was uploaded as. Files recorded before
+    /// images could be uploaded as anything else were uploaded as decals.
+    #[serde(default)]
+    pub asset_type: ImageAssetType,
+
+    /// The tags given with `--tags` when the file was last uploaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// The prefix given with `--name-prefix` when the file was last uploaded,
+    /// which `tarmac codegen` puts before the file's key.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name_prefix: String,
+}
+
+impl LockedUpload {
+    /// Uploading a file as a different type of asset, or with a different
+    /// name, gives a different asset even if the file hasn't changed.
+    fn was_uploaded_as(&self, asset_type: ImageAssetType, name_prefix: &str) -> bool {
+        self.asset_type == asset_type && self.name_prefix == name_prefix
+    }
+}
+
+/// Paths are stored with forward slashes so that lock files can be shared
+/// between platforms.
+fn lock_key(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+#[derive(Debug, Error)]
+pub enum UploadLockError {
+    #[error(
+        "Timed out waiting for another Tarmac process to release {}",
+        .file_path.display()
+    )]
+    Held { file_path: PathBuf },
+
+    #[error("Couldn't lock {}", .file_path.display())]
+    Lock {
+        file_path: PathBuf,
+        source: io::Error,
+    },
+
+    #[error("Error deserializing TOML from path {}", .file_path.display())]
+    DeserializeToml {
+        file_path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[error(transparent)]
+    SerializeToml {
+        #[from]
+        source: toml::ser::Error,
+    },
+
+    #[error(transparent)]
+    SerializeJson {
+        #[from]
+        source: serde_json::Error,
+    },
+
+    #[error(transparent)]
+    Io {
+        #[from]
+        source: io::Error,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn get_matching_hash() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("images/foo.png"),
+            "abc".to_owned(),
+            123,
+            ImageAssetType::Decal,
+        );
+
+        assert_eq!(
+            lock.get(
+                Path::new("images/foo.png"),
+                "abc",
+                ImageAssetType::Decal,
+                ""
+            ),
+            Some(123)
+        );
+        assert_eq!(
+            lock.get(
+                Path::new("images/foo.png"),
+                "def",
+                ImageAssetType::Decal,
+                ""
+            ),
+            None
+        );
+        assert_eq!(
+            lock.get(
+                Path::new("images/bar.png"),
+                "abc",
+                ImageAssetType::Decal,
+                ""
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn get_matching_asset_type() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("foo.png"),
+            "abc".to_owned(),
+            123,
+            ImageAssetType::Decal,
+        );
+
+        let path = Path::new("foo.png");
+        assert_eq!(lock.get(path, "abc", ImageAssetType::Decal, ""), Some(123));
+        assert_eq!(lock.get(path, "abc", ImageAssetType::Image, ""), None);
+        assert_eq!(lock.id_as(path, ImageAssetType::Image, ""), None);
+
+        // Locks from before asset types were recorded only have decals.
+        let lock: UploadLock = toml::from_str(
+            r#"
+            [uploads."foo.png"]
+            hash = "abc"
+            id = 123
+            "#,
+        )
+        .unwrap();
+        assert_eq!(lock.get(path, "abc", ImageAssetType::Decal, ""), Some(123));
+    }
+
+    #[test]
+    fn guard_excludes_others() {
+        let folder = std::env::temp_dir().join(format!(
+            "tarmac-guard_excludes_others-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&folder).unwrap();
+
+        let guard = UploadLockGuard::acquire_within(&folder, Duration::ZERO).unwrap();
+        assert!(matches!(
+            UploadLockGuard::acquire_within(&folder, Duration::ZERO),
+            Err(UploadLockError::Held { .. })
+        ));
+
+        // The guard file is left behind, but it's only the OS lock on it that
+        // keeps others out.
+        drop(guard);
+        let guard = UploadLockGuard::acquire_within(&folder, Duration::ZERO);
+        assert!(guard.is_ok());
+
+        drop(guard);
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("foo.png"),
+            "abc".to_owned(),
+            123,
+            ImageAssetType::Decal,
+        );
+
+        let serialized = toml::to_vec(&lock).unwrap();
+        let lock: UploadLock = toml::from_slice(&serialized).unwrap();
+
+        assert_eq!(
+            lock.get(Path::new("foo.png"), "abc", ImageAssetType::Decal, ""),
+            Some(123)
+        );
+    }
+
+    #[test]
+    fn manifest_of_uploaded_files() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("icons/save.png"),
+            "abc".to_owned(),
+            123,
+            ImageAssetType::Decal,
+        );
+        lock.insert(
+            Path::new("icons/load.png"),
+            "def".to_owned(),
+            456,
+            ImageAssetType::Decal,
+        );
+
+        let output_path = std::env::temp_dir().join("tarmac-manifest_of_uploaded_files.json");
+        let paths = [Path::new("icons/save.png"), Path::new("icons/failed.png")];
+        lock.write_manifest(paths.iter().copied(), &output_path)
+            .unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_slice(&fs::read(&output_path).unwrap()).unwrap();
+
+        assert_eq!(
+            manifest,
+            serde_json::json!({
+                "icons/save.png": { "hash": "abc", "id": 123, "asset-type": "decal" },
+            })
+        );
+    }
+
+    #[test]
+    fn tagged_uploads() {
+        let mut lock = UploadLock::default();
+        lock.insert(
+            Path::new("icons/save.png"),
+            "abc".to_owned(),
+            123,
+            ImageAssetType::Decal,
+        );
+        lock.insert(
+            Path::new("icons/load.png"),
+            "def".to_owned(),
+            456,
+            ImageAssetType::Decal,
+        );
+        lock.set_tags(
+            Path::new("icons/save.png"),
+            &["ui".to_owned(), "v2".to_owned()],
+        );
+        lock.set_tags(Path::new("icons/missing.png"), &["ui".to_owned()]);
+
+        let serialized = toml::to_vec(&lock).unwrap();
+        let lock: UploadLock = toml::from_slice(&serialized).unwrap();
+
+        assert_eq!(lock.ids_tagged("ui"), [123].iter().copied().collect());
+        assert!(lock.ids_tagged("v3").is_empty());
+        assert!(lock.uploads["icons/load.png"].tags.is_empty());
+    }
+
+    #[test]
+    fn changes_since_read() {
+        let mut lock: UploadLock = toml::from_str(
+            r#"
+            [uploads."foo.png"]
+            hash = "abc"
+            id = 123
+            tags = ["ui"]
+            "#,
+        )
+        .unwrap();
+        assert!(!lock.has_changes());
+
+        // Setting the tags or prefix an upload already has changes nothing.
+        lock.set_tags(Path::new("foo.png"), &["ui".to_owned()]);
+        lock.set_name_prefix(Path::new("foo.png"), "");
+        lock.set_tags(Path::new("missing.png"), &["v2".to_owned()]);
+        assert!(!lock.has_changes());
+
+        lock.set_tags(Path::new("foo.png"), &["ui".to_owned(), "v2".to_owned()]);
+        assert!(lock.has_changes());
+    }
+}
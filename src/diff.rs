@@ -0,0 +1,221 @@
+//! A small line-based diff, used to show what a command would change in a
+//! file without writing it.
+
+use std::fmt::Write;
+
+/// Lines of context shown around each change.
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Produces a unified diff between two texts, like `diff -u` does, labeling
+/// the old and new text with the given names. Returns `None` if the texts have
+/// the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> Option<String> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let edits = diff_lines(&old_lines, &new_lines);
+
+    if edits.iter().all(|edit| *edit == Edit::Equal) {
+        return None;
+    }
+
+    let mut output = String::new();
+    writeln!(output, "--- {}", old_name).unwrap();
+    writeln!(output, "+++ {}", new_name).unwrap();
+
+    for (start, end) in hunks(&edits) {
+        // The number of old and new lines before the hunk starts.
+        let old_before = count(&edits[..start], Edit::Insert);
+        let new_before = count(&edits[..start], Edit::Delete);
+        let old_count = count(&edits[start..end], Edit::Insert);
+        let new_count = count(&edits[start..end], Edit::Delete);
+
+        writeln!(
+            output,
+            "@@ -{} +{} @@",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        )
+        .unwrap();
+
+        let (mut old_index, mut new_index) = (old_before, new_before);
+
+        for edit in &edits[start..end] {
+            match edit {
+                Edit::Equal => {
+                    writeln!(output, " {}", old_lines[old_index]).unwrap();
+                    old_index += 1;
+                    new_index += 1;
+                }
+                Edit::Delete => {
+                    writeln!(output, "-{}", old_lines[old_index]).unwrap();
+                    old_index += 1;
+                }
+                Edit::Insert => {
+                    writeln!(output, "+{}", new_lines[new_index]).unwrap();
+                    new_index += 1;
+                }
+            }
+        }
+    }
+
+    Some(output)
+}
+
+/// Finds the shortest list of edits that turns `old` into `new`. Lines shared
+/// at the start and end are matched first, since generated files usually only
+/// change in a few places, which keeps the table used for the rest small.
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    // common[i][j] is the length of the longest common subsequence of
+    // old_middle[i..] and new_middle[j..].
+    let width = new_middle.len() + 1;
+    let mut common = vec![0u32; (old_middle.len() + 1) * width];
+
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            common[i * width + j] = if old_middle[i] == new_middle[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut edits = vec![Edit::Equal; prefix];
+    let (mut i, mut j) = (0, 0);
+
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            edits.push(Edit::Equal);
+            i += 1;
+            j += 1;
+        } else if j == new_middle.len()
+            || (i < old_middle.len() && common[(i + 1) * width + j] >= common[i * width + j + 1])
+        {
+            edits.push(Edit::Delete);
+            i += 1;
+        } else {
+            edits.push(Edit::Insert);
+            j += 1;
+        }
+    }
+
+    edits.resize(edits.len() + suffix, Edit::Equal);
+    edits
+}
+
+/// Groups changed lines into hunks, each a range of edits that includes some
+/// unchanged lines of context. Changes close enough to share context are put
+/// in the same hunk.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+
+    for (index, _) in edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| **edit != Edit::Equal)
+    {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + 1 + CONTEXT_LINES).min(edits.len());
+
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    hunks
+}
+
+fn count(edits: &[Edit], excluded: Edit) -> usize {
+    edits.iter().filter(|edit| **edit != excluded).count()
+}
+
+/// Formats the range of lines a hunk covers. Empty ranges start at the line
+/// before them, like other diff tools write them.
+fn hunk_range(before: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", before)
+    } else {
+        format!("{},{}", before + 1, count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn identical_texts() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), None);
+    }
+
+    #[test]
+    fn changed_line() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n";
+
+        assert_eq!(
+            unified_diff(old, new, "a.lua", "b.lua").unwrap(),
+            "--- a.lua\n\
+             +++ b.lua\n\
+             @@ -2,7 +2,7 @@\n \
+             2\n \
+             3\n \
+             4\n\
+             -5\n\
+             +five\n \
+             6\n \
+             7\n \
+             8\n"
+        );
+    }
+
+    #[test]
+    fn separate_hunks() {
+        let old: String = (1..=20).map(|line| format!("{}\n", line)).collect();
+        let new: String = (1..=20)
+            .filter(|line| *line != 2)
+            .map(|line| match line {
+                19 => "19\nnew\n".to_owned(),
+                _ => format!("{}\n", line),
+            })
+            .collect();
+
+        let diff = unified_diff(&old, &new, "old", "new").unwrap();
+        let headers: Vec<&str> = diff.lines().filter(|line| line.starts_with("@@")).collect();
+
+        assert_eq!(headers, vec!["@@ -1,5 +1,4 @@", "@@ -17,4 +16,5 @@"]);
+    }
+
+    #[test]
+    fn new_file() {
+        assert_eq!(
+            unified_diff("", "a\n", "old", "new").unwrap(),
+            "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
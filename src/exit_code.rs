@@ -0,0 +1,320 @@
+//! Exit codes that tell scripts what kind of failure stopped Tarmac, so that
+//! they can tell a missing cookie apart from a flaky network.
+
+use std::error::Error;
+
+use crate::{
+    commands::{
+        AlphaBleedError, CheckError, CodegenError, InfoError, InitError, ListError, PingError,
+        SpritesheetError, SyncError, ThumbnailError, UploadAudioError, UploadDirError,
+        UploadImageError, UploadListError, WhoamiError,
+    },
+    data::ConfigError,
+    roblox_web_api::RobloxApiError,
+    sync_backend::Error as SyncBackendError,
+};
+
+/// The kind of failure that made a command fail, which decides the exit code
+/// of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// Anything that isn't one of the other kinds, like a missing file.
+    Other,
+
+    /// The authentication cookie was missing, invalid, or not allowed to do
+    /// what was asked.
+    Auth,
+
+    /// Roblox moderated an asset.
+    Moderation,
+
+    /// Roblox couldn't be reached, timed out, rate limited us, or had an
+    /// error of its own.
+    Network,
+}
+
+impl FailureKind {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            FailureKind::Other => 1,
+            FailureKind::Auth => 2,
+            FailureKind::Moderation => 3,
+            FailureKind::Network => 4,
+        }
+    }
+
+    /// The kind shared by every one of the given failures, or `Other` if they
+    /// aren't all the same kind.
+    pub fn common<I>(kinds: I) -> FailureKind
+    where
+        I: IntoIterator<Item = FailureKind>,
+    {
+        let mut kinds = kinds.into_iter();
+        let first = kinds.next().unwrap_or(FailureKind::Other);
+
+        if kinds.all(|kind| kind == first) {
+            first
+        } else {
+            FailureKind::Other
+        }
+    }
+}
+
+/// Errors that know what kind of failure they are.
+pub trait Classify {
+    fn failure_kind(&self) -> FailureKind;
+}
+
+/// An error that stopped a command, along with what kind of failure it was.
+pub struct Failure {
+    pub kind: FailureKind,
+    pub error: anyhow::Error,
+}
+
+impl Failure {
+    /// Wraps an error that can't be classified any further.
+    pub fn other(error: anyhow::Error) -> Self {
+        Self {
+            kind: FailureKind::Other,
+            error,
+        }
+    }
+}
+
+impl<E> From<E> for Failure
+where
+    E: Classify + Error + Send + Sync + 'static,
+{
+    fn from(error: E) -> Self {
+        Self {
+            kind: error.failure_kind(),
+            error: error.into(),
+        }
+    }
+}
+
+impl Classify for RobloxApiError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            RobloxApiError::NoAuthCookie
+            | RobloxApiError::InvalidCookie { .. }
+            | RobloxApiError::NotAuthenticated
+            | RobloxApiError::Unauthorized { .. }
+            | RobloxApiError::CsrfTokenRejected
+            | RobloxApiError::NotInGroup { .. } => FailureKind::Auth,
+
+            RobloxApiError::ModerationRejected { .. } => FailureKind::Moderation,
+
+            RobloxApiError::Http { .. }
+            | RobloxApiError::Timeout { .. }
+            | RobloxApiError::ResponseLost { .. }
+            | RobloxApiError::RateLimited { .. } => FailureKind::Network,
+
+            RobloxApiError::ResponseError { status, .. } if status.is_server_error() => {
+                FailureKind::Network
+            }
+
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for UploadImageError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            UploadImageError::UploadBatch { source } => source.failure_kind(),
+            UploadImageError::UploadList { source } => source.failure_kind(),
+            UploadImageError::UploadAudio { source } => source.failure_kind(),
+            UploadImageError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for UploadDirError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            UploadDirError::HadErrors { failure_kind, .. } => *failure_kind,
+            UploadDirError::Upload { source, .. } => source.failure_kind(),
+            UploadDirError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for UploadListError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            UploadListError::HadErrors { failure_kind, .. } => *failure_kind,
+            UploadListError::Upload { source, .. } => source.failure_kind(),
+            UploadListError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for UploadAudioError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            UploadAudioError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for SpritesheetError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            SpritesheetError::Image { source } => source.failure_kind(),
+            SpritesheetError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for ListError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            ListError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for InfoError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            InfoError::RobloxApi { source } => source.failure_kind(),
+        }
+    }
+}
+
+impl Classify for ThumbnailError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            ThumbnailError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for WhoamiError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            WhoamiError::NotAuthenticated => FailureKind::Auth,
+            WhoamiError::RobloxApi { source } => source.failure_kind(),
+        }
+    }
+}
+
+impl Classify for PingError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            PingError::Unhealthy { .. } => FailureKind::Network,
+            PingError::RobloxApi { source } => source.failure_kind(),
+        }
+    }
+}
+
+impl Classify for SyncError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            SyncError::Backend { source } => source.failure_kind(),
+            SyncError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for SyncBackendError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            SyncBackendError::RateLimited => FailureKind::Network,
+            SyncBackendError::RobloxApi { source } => source.failure_kind(),
+            _ => FailureKind::Other,
+        }
+    }
+}
+
+impl Classify for CheckError {
+    fn failure_kind(&self) -> FailureKind {
+        FailureKind::Other
+    }
+}
+
+impl Classify for CodegenError {
+    fn failure_kind(&self) -> FailureKind {
+        FailureKind::Other
+    }
+}
+
+impl Classify for InitError {
+    fn failure_kind(&self) -> FailureKind {
+        FailureKind::Other
+    }
+}
+
+impl Classify for AlphaBleedError {
+    fn failure_kind(&self) -> FailureKind {
+        FailureKind::Other
+    }
+}
+
+impl Classify for ConfigError {
+    fn failure_kind(&self) -> FailureKind {
+        FailureKind::Other
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use reqwest::StatusCode;
+
+    #[test]
+    fn classify_wrapped_errors() {
+        let moderated = RobloxApiError::ModerationRejected {
+            asset_name: "foo".to_owned(),
+            message: "inappropriate".to_owned(),
+        };
+        let error = UploadImageError::UploadBatch {
+            source: Box::new(UploadDirError::Upload {
+                path: "foo.png".into(),
+                name: "foo".to_owned(),
+                source: Box::new(UploadImageError::RobloxApi { source: moderated }),
+            }),
+        };
+
+        assert_eq!(Failure::from(error).kind.exit_code(), 3);
+        assert_eq!(
+            Failure::from(ListError::from(RobloxApiError::NoAuthCookie))
+                .kind
+                .exit_code(),
+            2
+        );
+        assert_eq!(
+            Failure::from(WhoamiError::NotAuthenticated)
+                .kind
+                .exit_code(),
+            2
+        );
+
+        let server_error = RobloxApiError::ResponseError {
+            status: StatusCode::BAD_GATEWAY,
+            code: None,
+            message: None,
+            body: String::new(),
+        };
+        assert_eq!(server_error.failure_kind(), FailureKind::Network);
+    }
+
+    #[test]
+    fn common_kind() {
+        use FailureKind::*;
+
+        assert_eq!(FailureKind::common(vec![Network, Network]), Network);
+        assert_eq!(FailureKind::common(vec![Network, Auth]), Other);
+        assert_eq!(FailureKind::common(vec![]), Other);
+    }
+}
@@ -72,16 +72,21 @@ impl fmt::Display for Glob {
 // Glob syntax described here: https://docs.rs/globset/0.4.4/globset/#syntax
 const GLOB_PATTERN_CHARACTERS: &str = "*?{}[]";
 
+/// Whether the given string looks like it uses glob pattern syntax, rather
+/// than being a plain path.
+pub fn is_pattern(glob: &str) -> bool {
+    GLOB_PATTERN_CHARACTERS
+        .chars()
+        .any(|special_char| glob.contains(special_char))
+}
+
 fn get_non_pattern_prefix(glob_path: &Path) -> PathBuf {
     let mut prefix = PathBuf::new();
 
     for component in glob_path.iter() {
         let component_str = component.to_str().unwrap();
 
-        if GLOB_PATTERN_CHARACTERS
-            .chars()
-            .any(|special_char| component_str.contains(special_char))
-        {
+        if is_pattern(component_str) {
             break;
         }
 
@@ -119,6 +124,13 @@ mod test {
         );
     }
 
+    #[test]
+    fn pattern_detection() {
+        assert!(is_pattern("assets/**/*.png"));
+        assert!(is_pattern("icon-{a,b}.png"));
+        assert!(!is_pattern("assets/icon.png"));
+    }
+
     #[test]
     fn whole_path() {
         assert_eq!(
@@ -0,0 +1,28 @@
+//! Tarmac is a resource compiler and asset manager for Roblox projects.
+//!
+//! Most of Tarmac is its command line interface, which lives in `commands`.
+//! Other Rust tools can use [`Tarmac`] to upload images the same way that
+//! `tarmac upload-image` does, without anything being printed.
+
+mod alpha_bleed;
+mod alpha_channel;
+mod asset_name;
+mod auth_cookie;
+mod codegen;
+pub mod commands;
+pub mod data;
+mod diff;
+mod dpi_scale;
+pub mod exit_code;
+mod glob;
+mod lua_ast;
+pub mod options;
+mod roblox_web_api;
+mod secret;
+mod spritesheet;
+mod svg;
+mod sync_backend;
+mod uploader;
+
+pub use commands::{UploadImageError, UploadOutcome};
+pub use uploader::{Tarmac, TarmacOptions, UploadResult};
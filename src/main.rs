@@ -1,33 +1,45 @@
-mod alpha_bleed;
-mod asset_name;
-mod auth_cookie;
-mod codegen;
-mod commands;
-mod data;
-mod dpi_scale;
-mod glob;
-mod lua_ast;
-mod options;
-mod roblox_web_api;
-mod sync_backend;
-
 use std::{env, panic, process};
 
 use backtrace::Backtrace;
 use structopt::StructOpt;
 
-use crate::options::{Options, Subcommand};
+use tarmac::{
+    commands,
+    data::OptionsConfig,
+    exit_code::Failure,
+    options::{Options, Subcommand},
+};
+
+fn run(mut options: Options) -> Result<(), Failure> {
+    options.global.apply_config(OptionsConfig::load()?);
 
-fn run(options: Options) -> Result<(), anyhow::Error> {
     match options.command {
         Subcommand::UploadImage(upload_options) => {
-            commands::upload_image(options.global, upload_options)
+            commands::upload_image(options.global, upload_options)?
+        }
+        Subcommand::UploadAudio(upload_options) => {
+            commands::upload_audio(options.global, upload_options)?
         }
+        Subcommand::UploadDir(upload_options) => {
+            commands::upload_dir(options.global, upload_options)?
+        }
+        Subcommand::Init(sub_options) => commands::init(options.global, sub_options)?,
         Subcommand::Sync(sync_options) => commands::sync(options.global, sync_options)?,
         Subcommand::CreateCacheMap(sub_options) => {
-            commands::create_cache_map(options.global, sub_options)?
+            commands::create_cache_map(options.global, sub_options).map_err(Failure::other)?
+        }
+        Subcommand::AssetList(sub_options) => {
+            commands::asset_list(options.global, sub_options).map_err(Failure::other)?
         }
-        Subcommand::AssetList(sub_options) => commands::asset_list(options.global, sub_options)?,
+        Subcommand::Codegen(sub_options) => commands::codegen(options.global, sub_options)?,
+        Subcommand::Info(sub_options) => commands::info(options.global, sub_options)?,
+        Subcommand::List(sub_options) => commands::list(options.global, sub_options)?,
+        Subcommand::Thumbnail(sub_options) => commands::thumbnail(options.global, sub_options)?,
+        Subcommand::Whoami => commands::whoami(options.global)?,
+        Subcommand::Ping => commands::ping(options.global)?,
+        Subcommand::Check(sub_options) => commands::check(options.global, sub_options)?,
+        Subcommand::Spritesheet(sub_options) => commands::spritesheet(options.global, sub_options)?,
+        Subcommand::AlphaBleed(sub_options) => commands::alpha_bleed(options.global, sub_options)?,
     }
 
     Ok(())
@@ -83,6 +95,7 @@ fn main() {
     let options = Options::from_args();
 
     let log_filter = match options.global.verbosity {
+        _ if options.global.quiet => "error",
         0 => "info",
         1 => "info,tarmac=debug",
         2 => "info,tarmac=trace",
@@ -98,8 +111,8 @@ fn main() {
         .format_indent(Some(8))
         .init();
 
-    if let Err(err) = run(options) {
-        log::error!("{:?}", err);
-        process::exit(1);
+    if let Err(failure) = run(options) {
+        log::error!("{:?}", failure.error);
+        process::exit(failure.kind.exit_code());
     }
 }
@@ -1,7 +1,17 @@
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    convert::TryFrom,
+    env, fmt,
+    path::PathBuf,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
+use crate::{data::OptionsConfig, secret::Secret};
+
 #[derive(Debug, StructOpt)]
 #[structopt(about = env!("CARGO_PKG_DESCRIPTION"))]
 pub struct Options {
@@ -12,25 +22,307 @@ pub struct Options {
     pub command: Subcommand,
 }
 
-#[derive(Debug, StructOpt)]
+#[derive(Debug, Clone, StructOpt)]
 pub struct GlobalOptions {
-    /// The authentication cookie for Tarmac to use. If not specified, Tarmac
-    /// will attempt to use the cookie from the Roblox Studio installation on
-    /// the system.
+    /// The authentication cookie for Tarmac to use. Can also be set with the
+    /// `TARMAC_AUTH` environment variable. If neither is given, Tarmac will
+    /// attempt to use the cookie from the Roblox Studio installation on the
+    /// system.
+    #[structopt(long, global(true), env = "TARMAC_AUTH", hide_env_values = true)]
+    pub auth: Option<Secret>,
+
+    /// The domain that Tarmac should send Roblox API requests to, like
+    /// `sitetest1.robloxlabs.com`. Can also be set with the `TARMAC_API_BASE`
+    /// environment variable. Defaults to `roblox.com`.
+    #[structopt(long, global(true))]
+    pub api_base: Option<String>,
+
+    /// The URL of an HTTP or HTTPS proxy that Tarmac should send all of its
+    /// requests through.
+    #[structopt(long, global(true))]
+    pub proxy: Option<String>,
+
+    /// The User-Agent header that Tarmac should send with its requests.
+    /// Defaults to `tarmac/<version>`.
+    #[structopt(long, global(true))]
+    pub user_agent: Option<String>,
+
+    /// An extra header to send with every request to Roblox, written like
+    /// `Name: value`, for gateways that need headers of their own. Can be
+    /// given more than once.
+    #[structopt(
+        long = "header",
+        global(true),
+        number_of_values = 1,
+        value_name = "name:value",
+        parse(try_from_str = parse_header)
+    )]
+    pub extra_headers: Vec<(String, String)>,
+
+    /// The number of times Tarmac will retry a request to Roblox that failed
+    /// because of a network or server error. Each retry waits twice as long as
+    /// the previous one, starting at 250 milliseconds. Defaults to 3.
+    #[structopt(long, global(true))]
+    pub retries: Option<usize>,
+
+    /// How Tarmac retries requests to Roblox that failed because of a network
+    /// or server error, instead of --retries: `none`,
+    /// `fixed:<retries>:<delay-ms>` to wait the same time before each retry,
+    /// or `exponential:<retries>:<base-ms>` to double the wait each time.
+    /// Defaults to `exponential:3:250`.
+    #[structopt(long, global(true), conflicts_with = "retries")]
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// The longest number of seconds Tarmac will wait before retrying a
+    /// request that Roblox rate limited, even if Roblox asks for a longer wait.
+    /// Defaults to 60.
     #[structopt(long, global(true))]
-    pub auth: Option<String>,
+    pub max_retry_after: Option<u64>,
+
+    /// The number of seconds a request to Roblox may take before Tarmac gives
+    /// up on it. Timed out requests are retried like other network errors.
+    /// Defaults to 60.
+    #[structopt(long, global(true))]
+    pub timeout: Option<u64>,
+
+    /// The number of seconds Tarmac will wait to connect to Roblox before
+    /// giving up. Defaults to 10.
+    #[structopt(long, global(true))]
+    pub connect_timeout: Option<u64>,
+
+    /// Don't check that the authentication cookie is valid, and that the user
+    /// is a member of the group being uploaded to, before the first upload.
+    #[structopt(long, global(true))]
+    pub no_validate_creator: bool,
+
+    /// Check that files can be uploaded, and that authentication is present,
+    /// without uploading anything. Applies to `upload-image`, `upload-dir`,
+    /// and `upload-audio`.
+    #[structopt(long, global(true))]
+    pub dry_run: bool,
+
+    /// How upload commands should report the assets they upload: `text`
+    /// prints just asset IDs, and `json` prints a JSON object for each file.
+    /// Messages meant for people are always printed to stderr.
+    #[structopt(long, global(true), default_value = "text")]
+    pub output_format: OutputFormat,
+
+    /// Only print results and errors. Progress and success messages aren't
+    /// printed, and neither are log messages below the error level.
+    #[structopt(long, short, global(true), conflicts_with = "verbosity")]
+    pub quiet: bool,
 
     /// Sets verbosity level. Can be specified multiple times.
     #[structopt(long = "verbose", short, global(true), parse(from_occurrences))]
     pub verbosity: u8,
 }
 
+impl GlobalOptions {
+    /// Fills in any options that weren't given on the command line from the
+    /// `[options]` table of a tarmac.toml, and then from environment variables.
+    pub fn apply_config(&mut self, config: OptionsConfig) {
+        self.api_base = self
+            .api_base
+            .take()
+            .or(config.api_base)
+            .or_else(|| env::var("TARMAC_API_BASE").ok());
+        self.proxy = self.proxy.take().or(config.proxy);
+        self.user_agent = self.user_agent.take().or(config.user_agent);
+
+        // The two retry options replace each other, so the config's are only
+        // used if neither was given on the command line.
+        if self.retries.is_none() && self.retry_policy.is_none() {
+            self.retries = config.retries;
+            self.retry_policy = config.retry_policy;
+        }
+
+        self.max_retry_after = self.max_retry_after.or(config.max_retry_after);
+        self.timeout = self.timeout.or(config.timeout);
+        self.connect_timeout = self.connect_timeout.or(config.connect_timeout);
+    }
+}
+
+/// How requests that fail for a transient reason are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RetryPolicy {
+    /// Requests are never retried.
+    None,
+
+    /// Requests are retried up to `attempts` times, waiting `delay` before
+    /// each retry.
+    Fixed { attempts: usize, delay: Duration },
+
+    /// Requests are retried up to `attempts` times. The wait before the first
+    /// retry is up to `base`, and doubles for each retry after it. Each wait
+    /// is picked at random, so that requests that failed together don't
+    /// retry together.
+    Exponential { attempts: usize, base: Duration },
+}
+
+impl RetryPolicy {
+    /// Retries the given number of times with exponential backoff, starting
+    /// from the default delay.
+    pub fn exponential(attempts: usize) -> Self {
+        RetryPolicy::Exponential {
+            attempts,
+            base: Duration::from_millis(250),
+        }
+    }
+
+    /// The most times a request is retried.
+    pub fn attempts(self) -> usize {
+        match self {
+            RetryPolicy::None => 0,
+            RetryPolicy::Fixed { attempts, .. } => attempts,
+            RetryPolicy::Exponential { attempts, .. } => attempts,
+        }
+    }
+
+    /// The longest time to wait before the retry that follows the given
+    /// number of failed retries.
+    pub fn max_delay(self, attempt: usize) -> Duration {
+        match self {
+            RetryPolicy::None => Duration::from_secs(0),
+            RetryPolicy::Fixed { delay, .. } => delay,
+            RetryPolicy::Exponential { base, .. } => base * 2u32.saturating_pow(attempt as u32),
+        }
+    }
+
+    /// How long to wait before the retry that follows the given number of
+    /// failed retries.
+    pub fn delay<R: Rng>(self, attempt: usize, rng: &mut R) -> Duration {
+        let max_delay = self.max_delay(attempt);
+
+        match self {
+            RetryPolicy::Exponential { .. } => {
+                Duration::from_millis(rng.gen_range(0..=max_delay.as_millis() as u64))
+            }
+            _ => max_delay,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::exponential(3)
+    }
+}
+
+impl FromStr for RetryPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<RetryPolicy, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid retry policy {:?}. Valid options are none, \
+                 fixed:<retries>:<delay-ms>, and exponential:<retries>:<base-ms>.",
+                value
+            )
+        };
+
+        let mut pieces = value.split(':');
+        let kind = pieces.next().unwrap_or_default();
+
+        if kind == "none" {
+            return match pieces.next() {
+                None => Ok(RetryPolicy::None),
+                Some(_) => Err(invalid()),
+            };
+        }
+
+        let (attempts, millis) = match (pieces.next(), pieces.next(), pieces.next()) {
+            (Some(attempts), Some(millis), None) => (
+                attempts.parse().map_err(|_| invalid())?,
+                millis.parse().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+
+        match kind {
+            "fixed" => Ok(RetryPolicy::Fixed {
+                attempts,
+                delay: Duration::from_millis(millis),
+            }),
+            "exponential" => Ok(RetryPolicy::Exponential {
+                attempts,
+                base: Duration::from_millis(millis),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RetryPolicy::None => write!(formatter, "none"),
+            RetryPolicy::Fixed { attempts, delay } => {
+                write!(formatter, "fixed:{}:{}", attempts, delay.as_millis())
+            }
+            RetryPolicy::Exponential { attempts, base } => {
+                write!(formatter, "exponential:{}:{}", attempts, base.as_millis())
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for RetryPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<RetryPolicy> for String {
+    fn from(policy: RetryPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<OutputFormat, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+
+            _ => Err(String::from(
+                "Invalid output format. Valid options are text and json.",
+            )),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 pub enum Subcommand {
     /// Upload a single image to the Roblox cloud. Prints the asset ID of the
     /// resulting Image asset to stdout.
+    ///
+    /// MP3 and OGG files are uploaded as audio instead, like `upload-audio`.
     UploadImage(UploadImageOptions),
 
+    /// Upload a single MP3 or OGG file to the Roblox cloud. Prints the asset
+    /// ID of the resulting Audio asset to stdout.
+    UploadAudio(UploadAudioOptions),
+
+    /// Upload every image in a directory to the Roblox cloud, naming each
+    /// asset after its file. Prints the path and asset ID of each image to
+    /// stdout.
+    UploadDir(UploadDirOptions),
+
+    /// Creates a tarmac.toml with commented examples of the settings a project
+    /// can use, to start a new project from.
+    Init(InitOptions),
+
     /// Sync your Tarmac project, uploading any assets that have changed.
     Sync(SyncOptions),
 
@@ -40,20 +332,471 @@ pub enum Subcommand {
 
     /// Creates a file that lists all assets required by the project.
     AssetList(AssetListOptions),
+
+    /// Generates a Luau module mapping the names of images uploaded with
+    /// `upload-image` or `upload-dir` to their asset URLs.
+    Codegen(CodegenOptions),
+
+    /// Prints the name, type, and creator of an existing asset.
+    Info(InfoOptions),
+
+    /// Lists the images, decals, and audio owned by a user, printing the ID,
+    /// type, and name of each.
+    List(ListOptions),
+
+    /// Downloads the thumbnail of an asset as a PNG, to check what an upload
+    /// looks like.
+    Thumbnail(ThumbnailOptions),
+
+    /// Checks that the authentication cookie works by printing the user it
+    /// belongs to and the groups they're in, without uploading anything.
+    Whoami,
+
+    /// Checks that Roblox can be reached, without authentication, by printing
+    /// how quickly it responds and with what status.
+    Ping,
+
+    /// Checks that images can be uploaded, by decoding, checking, and
+    /// encoding them like `upload-image` would, without uploading them. Needs
+    /// no authentication.
+    Check(CheckOptions),
+
+    /// Packs every image in a directory into spritesheets, uploads them, and
+    /// writes a JSON manifest describing where each image is.
+    Spritesheet(SpritesheetOptions),
+
+    /// Bleeds the colors of images into their transparent pixels, like
+    /// `upload-image` does, and writes the results to PNG files instead of
+    /// uploading them.
+    AlphaBleed(AlphaBleedOptions),
 }
 
 #[derive(Debug, StructOpt)]
 pub struct UploadImageOptions {
-    /// The path to the image to upload.
+    /// The path to the image to upload. Glob patterns like `assets/**/*.png`
+    /// upload every image that they match. Pass `-` to read the image from
+    /// stdin instead.
+    #[structopt(required_unless_one = &["stdin", "from-file"])]
+    pub path: Option<PathBuf>,
+
+    /// Read the image to upload from stdin, like passing `-` as the path.
+    /// Requires --name.
+    #[structopt(long, conflicts_with = "path")]
+    pub stdin: bool,
+
+    /// The name to give to the resulting Decal asset. Defaults to the name of
+    /// the file without its extension. Can't be used with a glob pattern.
+    #[structopt(long)]
+    pub name: Option<String>,
+
+    /// Upload every image listed in this file instead, one per line, like
+    /// `icons/save.png | Save | The save icon | 123`. Each line has a path,
+    /// and optionally a name, a description, an asset ID that the image was
+    /// already uploaded to, and settings that replace the ones given on the
+    /// command line for that image, like `asset-type=image, no-alpha-bleed`.
+    /// Lines starting with `#` are ignored.
+    #[structopt(long, conflicts_with_all = &["path", "stdin", "name"])]
+    pub from_file: Option<PathBuf>,
+
+    /// With --from-file, stop at the first entry that's malformed or fails to
+    /// upload instead of carrying on with the rest of the file.
+    #[structopt(long, requires = "from-file")]
+    pub fail_fast: bool,
+
+    #[structopt(flatten)]
+    pub settings: ImageUploadSettings,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UploadDirOptions {
+    /// The path to the directory of images to upload.
     pub path: PathBuf,
 
-    /// The name to give to the resulting Decal asset.
+    #[structopt(flatten)]
+    pub settings: ImageUploadSettings,
+
+    /// The number of images to upload at once.
+    #[structopt(long, default_value = "1")]
+    pub jobs: usize,
+
+    /// Stop at the first image that fails to upload instead of carrying on
+    /// with the rest of the directory.
     #[structopt(long)]
-    pub name: String,
+    pub fail_fast: bool,
+
+    /// Skip images that haven't been modified since this time, given either
+    /// as how long ago, like `2h` or `1day`, or as a UTC timestamp, like
+    /// `2021-01-30T12:00:00Z`. Skipped images that are in `tarmac-lock.toml`
+    /// are reported as unchanged without being read.
+    #[structopt(long, parse(try_from_str = parse_since))]
+    pub since: Option<SystemTime>,
+}
 
+/// Options that control how images are processed and uploaded, shared by
+/// `upload-image` and `upload-dir`.
+#[derive(Debug, Clone, StructOpt)]
+pub struct ImageUploadSettings {
     /// The description to give to the resulting Decal asset.
     #[structopt(long, default_value = "Uploaded by Tarmac.")]
     pub description: String,
+
+    /// A description to give to each asset, where `{name}` is replaced with
+    /// the asset's name and `{path}` with the path of its file. Overrides
+    /// --description.
+    #[structopt(long, conflicts_with = "description")]
+    pub description_template: Option<String>,
+
+    /// The largest width or height that the image may have. Roblox does not
+    /// accept images larger than 1024x1024.
+    #[structopt(long, default_value = "1024")]
+    pub max_size: u32,
+
+    /// Downscale images that are larger than the maximum size instead of
+    /// failing to upload them.
+    #[structopt(long)]
+    pub resize: bool,
+
+    /// When uploading an SVG, the length in pixels that the longest side of the
+    /// rasterized image should have. Defaults to the SVG's own size.
+    #[structopt(long)]
+    pub svg_size: Option<u32>,
+
+    /// Upload the image even if it hasn't changed since the last time it was
+    /// uploaded according to `tarmac-lock.toml`.
+    #[structopt(long)]
+    pub force: bool,
+
+    /// Composite the image over the given color, written in hex like
+    /// `#ffffff`, and upload it without an alpha channel.
+    #[structopt(long, value_name = "color")]
+    pub flatten: Option<HexColor>,
+
+    /// Upload the image with premultiplied alpha instead of straight alpha.
+    #[structopt(long, conflicts_with = "flatten")]
+    pub premultiply_alpha: bool,
+
+    /// Don't change the color of fully transparent pixels, which Tarmac does
+    /// by default to avoid dark edges when Roblox resizes images. Useful for
+    /// images that have to be uploaded exactly as they are.
+    #[structopt(long)]
+    pub no_alpha_bleed: bool,
+
+    #[structopt(flatten)]
+    pub bleed: BleedOptions,
+
+    /// How hard to compress uploaded PNGs: fast, default, or best. Better
+    /// compression makes uploads smaller, but takes longer.
+    #[structopt(long, default_value = "fast")]
+    pub png_compression: PngCompression,
+
+    /// The filter applied to each row of uploaded PNGs before compressing
+    /// them: none, sub, up, avg, or paeth. Which is best depends on the image.
+    #[structopt(long, default_value = "sub")]
+    pub png_filter: PngFilter,
+
+    /// The largest size in bytes that an encoded image may have. Roblox
+    /// doesn't accept images larger than 20 MB.
+    #[structopt(long, default_value = "20971520")]
+    pub max_file_size: usize,
+
+    /// If an encoded image is larger than --max-file-size, encode it again
+    /// with the best compression before failing to upload it.
+    #[structopt(long)]
+    pub recompress: bool,
+
+    /// After uploading each image, download it again and check that Roblox
+    /// stored the same pixels that were uploaded.
+    #[structopt(long)]
+    pub verify: bool,
+
+    /// Also write each processed image to this directory as a PNG, at the
+    /// same relative path as the image it came from, to inspect exactly what
+    /// was uploaded.
+    #[structopt(long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Fail to upload an image if it can't be written to --output-dir,
+    /// instead of only warning about it.
+    #[structopt(long, requires = "output-dir")]
+    pub strict: bool,
+
+    /// The type of asset to upload images as: decal or image. Decals get a
+    /// separate backing image, while images can be used directly.
+    #[structopt(long, default_value = "decal")]
+    pub asset_type: ImageAssetType,
+
+    /// After uploading, write a JSON file to this path describing the hash
+    /// and asset ID of every image that was uploaded or already up to date.
+    #[structopt(long)]
+    pub manifest_out: Option<PathBuf>,
+
+    /// Tags to record with each image in `tarmac-lock.toml` and the
+    /// manifest, separated by commas, like `ui,v2`. Tags are made of letters,
+    /// numbers, dashes, underscores, and periods. `tarmac list --tag` can
+    /// filter by them.
+    #[structopt(long, use_delimiter = true, parse(try_from_str = parse_tag))]
+    pub tags: Vec<String>,
+
+    /// Text to put before the name of every uploaded asset, like `ui/`. The
+    /// prefix is recorded in `tarmac-lock.toml`, and `tarmac codegen` puts it
+    /// before the keys of those assets too.
+    #[structopt(long, default_value = "", hide_default_value = true)]
+    pub name_prefix: String,
+
+    /// Hold a lock on `tarmac-lock.toml` while uploading, so that other
+    /// Tarmac processes in the same directory wait for this one to finish
+    /// instead of overwriting its changes.
+    #[structopt(long)]
+    pub concurrency_safe: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+impl FromStr for PngCompression {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<PngCompression, Self::Err> {
+        match value {
+            "fast" => Ok(PngCompression::Fast),
+            "default" => Ok(PngCompression::Default),
+            "best" => Ok(PngCompression::Best),
+
+            _ => Err(String::from(
+                "Invalid PNG compression. Valid options are fast, default, and best.",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PngFilter {
+    None,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+}
+
+impl FromStr for PngFilter {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<PngFilter, Self::Err> {
+        match value {
+            "none" => Ok(PngFilter::None),
+            "sub" => Ok(PngFilter::Sub),
+            "up" => Ok(PngFilter::Up),
+            "avg" => Ok(PngFilter::Avg),
+            "paeth" => Ok(PngFilter::Paeth),
+
+            _ => Err(String::from(
+                "Invalid PNG filter. Valid options are none, sub, up, avg, and paeth.",
+            )),
+        }
+    }
+}
+
+/// The kind of asset that images are uploaded as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageAssetType {
+    /// A raw image, which can be used directly by UI objects.
+    Image,
+
+    /// A decal, which Roblox creates a backing image for.
+    #[default]
+    Decal,
+}
+
+impl ImageAssetType {
+    /// The ID Roblox uses for this asset type.
+    pub fn asset_type_id(self) -> u32 {
+        match self {
+            ImageAssetType::Image => 1,
+            ImageAssetType::Decal => 13,
+        }
+    }
+}
+
+impl FromStr for ImageAssetType {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ImageAssetType, Self::Err> {
+        match value {
+            "image" => Ok(ImageAssetType::Image),
+            "decal" => Ok(ImageAssetType::Decal),
+
+            _ => Err(String::from(
+                "Invalid asset type. Valid options are image and decal.",
+            )),
+        }
+    }
+}
+
+/// What to replace when uploading an image again after Roblox moderates it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationFallback {
+    Name,
+    Description,
+}
+
+impl FromStr for ModerationFallback {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ModerationFallback, Self::Err> {
+        match value {
+            "name" => Ok(ModerationFallback::Name),
+            "description" => Ok(ModerationFallback::Description),
+
+            _ => Err(String::from(
+                "Invalid moderation fallback. Valid options are name and description.",
+            )),
+        }
+    }
+}
+
+/// Parses the alpha that pixels need to count as opaque when bleeding. Zero
+/// isn't allowed, since every pixel would count as opaque.
+fn parse_bleed_threshold(value: &str) -> Result<u8, String> {
+    match value.parse() {
+        Ok(threshold) if threshold > 0 => Ok(threshold),
+        _ => Err(format!(
+            "Invalid bleed threshold {:?}. The threshold is a number from 1 to 255.",
+            value
+        )),
+    }
+}
+
+/// Splits a header given with `--header` into its name and value. Whether
+/// they're valid in an HTTP request is checked when the client is created.
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), value.trim().to_owned()))
+        }
+        _ => Err(format!(
+            "Invalid header {:?}. Headers are written like \"Name: value\".",
+            value
+        )),
+    }
+}
+
+/// Parses a point in time given either as a duration before now or as a UTC
+/// timestamp.
+fn parse_since(value: &str) -> Result<SystemTime, String> {
+    if let Ok(time) = humantime::parse_rfc3339_weak(value) {
+        return Ok(time);
+    }
+
+    let duration = humantime::parse_duration(value).map_err(|_| {
+        format!(
+            "Invalid time '{}'. Expected a duration like 2h or a timestamp like 2021-01-30T12:00:00Z.",
+            value
+        )
+    })?;
+
+    SystemTime::now()
+        .checked_sub(duration)
+        .ok_or_else(|| format!("Invalid time '{}'. It's too long ago.", value))
+}
+
+/// Parses the ID of a user or group. Roblox IDs start at 1, so 0 is rejected
+/// here instead of being sent to Roblox.
+fn parse_creator_id(value: &str) -> Result<u64, String> {
+    match value.parse() {
+        Ok(0) => Err(String::from("Invalid ID 0. User and group IDs start at 1.")),
+        Ok(id) => Ok(id),
+        Err(_) => Err(format!(
+            "Invalid ID {:?}. User and group IDs are whole numbers, like 1234567.",
+            value
+        )),
+    }
+}
+
+/// The sizes that Roblox makes asset thumbnails in.
+const THUMBNAIL_SIZES: &[&str] = &[
+    "30x30", "42x42", "50x50", "60x62", "75x75", "110x110", "140x140", "150x150", "160x100",
+    "160x600", "250x250", "256x144", "300x250", "304x166", "384x216", "396x216", "420x420",
+    "480x270", "512x512", "576x324", "700x700", "728x90", "768x432",
+];
+
+fn parse_thumbnail_size(value: &str) -> Result<String, String> {
+    if THUMBNAIL_SIZES.contains(&value) {
+        Ok(value.to_owned())
+    } else {
+        Err(format!(
+            "Invalid thumbnail size {:?}. Valid sizes are {}.",
+            value,
+            THUMBNAIL_SIZES.join(", ")
+        ))
+    }
+}
+
+/// The longest tag that can be given to an upload.
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Parses a tag given to an upload, which has to be short and made only of
+/// characters that are safe to use anywhere.
+fn parse_tag(value: &str) -> Result<String, String> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+
+    if value.is_empty() || value.len() > MAX_TAG_LENGTH || !value.chars().all(valid_char) {
+        return Err(format!(
+            "Invalid tag {:?}. Tags are 1 to {} letters, numbers, dashes, underscores, or periods.",
+            value, MAX_TAG_LENGTH
+        ));
+    }
+
+    Ok(value.to_owned())
+}
+
+/// A color written in hex, like `#ff8000` or `ff8000`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexColor(pub [u8; 3]);
+
+impl FromStr for HexColor {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<HexColor, Self::Err> {
+        let digits = value.strip_prefix('#').unwrap_or(value);
+        let invalid = || {
+            format!(
+                "Invalid color {:?}. Colors are written in hex, like #ffffff.",
+                value
+            )
+        };
+
+        if digits.len() != 6 || !digits.is_ascii() {
+            return Err(invalid());
+        }
+
+        let mut color = [0; 3];
+
+        for (i, channel) in color.iter_mut().enumerate() {
+            *channel = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        }
+
+        Ok(HexColor(color))
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UploadAudioOptions {
+    /// The path to the audio file to upload.
+    pub path: PathBuf,
+
+    /// The name to give to the resulting Audio asset.
+    #[structopt(long)]
+    pub name: String,
+
+    /// If specified, uploads the audio to the given Roblox Group instead of
+    /// the authenticated user.
+    #[structopt(long, parse(try_from_str = parse_creator_id))]
+    pub group_id: Option<u64>,
 }
 
 #[derive(Debug, StructOpt)]
@@ -82,6 +825,23 @@ pub struct SyncOptions {
     #[structopt(long, default_value = "60")]
     pub retry_delay: u64,
 
+    /// How many times to upload an image again after Roblox moderates it.
+    #[structopt(long, default_value = "1")]
+    pub max_retries_on_moderation: u32,
+
+    /// What to replace with a generic value before each upload after Roblox
+    /// moderates an image, separated by commas, like `description,name`.
+    /// Replacements add up, and the last one is repeated if there are more
+    /// retries than replacements.
+    ///
+    /// Options:
+    ///
+    /// - name: Replace the name of the image with "image".
+    ///
+    /// - description: Remove the description of the image.
+    #[structopt(long, use_delimiter = true, default_value = "name")]
+    pub moderation_fallbacks: Vec<ModerationFallback>,
+
     /// The path to a Tarmac config, or a folder containing a Tarmac project.
     pub config_path: Option<PathBuf>,
 }
@@ -132,3 +892,421 @@ pub struct AssetListOptions {
     #[structopt(long = "output")]
     pub output: PathBuf,
 }
+
+#[derive(Debug, StructOpt)]
+pub struct SpritesheetOptions {
+    /// The path to the directory of images to pack.
+    pub path: PathBuf,
+
+    /// A path to a file to put the JSON manifest describing the uploaded
+    /// spritesheets and the position of each image in them.
+    #[structopt(long)]
+    pub output: PathBuf,
+
+    /// The name to give each spritesheet, followed by its index.
+    #[structopt(long, default_value = "spritesheet")]
+    pub name: String,
+
+    /// The description to give to each spritesheet.
+    #[structopt(long, default_value = "Uploaded by Tarmac.")]
+    pub description: String,
+
+    /// The largest width or height that a spritesheet may have.
+    #[structopt(long, default_value = "1024")]
+    pub max_size: u32,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct InfoOptions {
+    /// The ID of the asset to look up.
+    pub asset_id: u64,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct AlphaBleedOptions {
+    /// The image to bleed. Directories and glob patterns like
+    /// `assets/**/*.png` bleed every image that they contain or match.
+    pub input: PathBuf,
+
+    /// Where to write the bled PNG. When the input is a directory or a glob
+    /// pattern, this is a directory that the images are written to, laid out
+    /// like the input.
+    pub output: PathBuf,
+
+    #[structopt(flatten)]
+    pub bleed: BleedOptions,
+}
+
+/// How colors are bled into the transparent pixels of images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, StructOpt)]
+pub struct BleedOptions {
+    /// The most rings of pixels around each opaque area to bleed colors into.
+    /// By default, colors are bled until every transparent pixel is filled.
+    #[structopt(long = "bleed-passes")]
+    pub passes: Option<u32>,
+
+    /// The alpha, from 1 to 255, that pixels need to be counted as opaque.
+    /// Colors are bled from opaque pixels into the pixels that aren't, which
+    /// are only fully transparent pixels by default.
+    #[structopt(
+        long = "bleed-threshold",
+        default_value = "1",
+        parse(try_from_str = parse_bleed_threshold)
+    )]
+    pub threshold: u8,
+}
+
+impl Default for BleedOptions {
+    fn default() -> Self {
+        Self {
+            passes: None,
+            threshold: 1,
+        }
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CheckOptions {
+    /// The images to check. Directories and glob patterns like
+    /// `assets/**/*.png` check every image that they contain or match.
+    #[structopt(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    #[structopt(flatten)]
+    pub settings: ImageUploadSettings,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ThumbnailOptions {
+    /// The ID of the asset whose thumbnail should be downloaded.
+    pub asset_id: u64,
+
+    /// The path to write the thumbnail to.
+    #[structopt(long)]
+    pub out: PathBuf,
+
+    /// The size of the thumbnail, like `420x420`. Roblox only makes
+    /// thumbnails in a few sizes, from 30x30 up to 700x700.
+    #[structopt(long, default_value = "420x420", parse(try_from_str = parse_thumbnail_size))]
+    pub size: String,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ListOptions {
+    /// The ID of the user whose assets should be listed. Defaults to the user
+    /// that the authentication cookie belongs to.
+    #[structopt(long, parse(try_from_str = parse_creator_id))]
+    pub user_id: Option<u64>,
+
+    /// The most assets to list. Every asset is listed by default.
+    #[structopt(long)]
+    pub limit: Option<usize>,
+
+    /// Only list assets that were uploaded with this tag, according to
+    /// `tarmac-lock.toml`.
+    #[structopt(long, parse(try_from_str = parse_tag))]
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct InitOptions {
+    /// The folder to create the tarmac.toml in. Defaults to the working
+    /// directory.
+    #[structopt(default_value = ".")]
+    pub path: PathBuf,
+
+    /// The name of the project. Defaults to the name of the folder.
+    #[structopt(long)]
+    pub name: Option<String>,
+
+    /// The ID of the group that the project's assets should be uploaded to.
+    /// Assets are uploaded to the authenticated user if this isn't given.
+    #[structopt(long, parse(try_from_str = parse_creator_id))]
+    pub group_id: Option<u64>,
+
+    /// Replace the tarmac.toml in the folder if there is one already.
+    #[structopt(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CodegenOptions {
+    /// A path to a file to put the generated module.
+    #[structopt(long = "output")]
+    pub output: PathBuf,
+
+    /// How to name each asset in the generated module: `path` uses the path
+    /// that the image was uploaded from, and `stem` uses just its file name.
+    /// Both leave off the file's extension.
+    #[structopt(long, default_value = "path")]
+    pub key_style: KeyStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyStyle {
+    Path,
+    Stem,
+}
+
+impl FromStr for KeyStyle {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<KeyStyle, Self::Err> {
+        match value {
+            "path" => Ok(KeyStyle::Path),
+            "stem" => Ok(KeyStyle::Stem),
+
+            _ => Err(String::from(
+                "Invalid key style. Valid options are path and stem.",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn parse_hex_color() {
+        assert_eq!("#ff8000".parse(), Ok(HexColor([255, 128, 0])));
+        assert_eq!("00FF7f".parse(), Ok(HexColor([0, 255, 127])));
+
+        assert!("#fff".parse::<HexColor>().is_err());
+        assert!("#gggggg".parse::<HexColor>().is_err());
+        assert!("#ff800é".parse::<HexColor>().is_err());
+    }
+
+    #[test]
+    fn extra_headers() {
+        let options = Options::from_iter(&[
+            "tarmac",
+            "--header",
+            "X-Gateway-Key: abc",
+            "--header=X-Origin:https://example.com",
+            "list",
+        ]);
+
+        assert_eq!(
+            options.global.extra_headers,
+            vec![
+                ("X-Gateway-Key".to_owned(), "abc".to_owned()),
+                ("X-Origin".to_owned(), "https://example.com".to_owned()),
+            ]
+        );
+
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header(": value").is_err());
+        assert_eq!(
+            parse_header("X-Empty:"),
+            Ok(("X-Empty".to_owned(), String::new()))
+        );
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let mut options = Options::from_iter(&[
+            "tarmac",
+            "--proxy",
+            "http://command-line",
+            "asset-list",
+            "--output",
+            "list.txt",
+        ]);
+
+        options.global.apply_config(OptionsConfig {
+            proxy: Some("http://config".to_owned()),
+            retries: Some(5),
+            ..OptionsConfig::default()
+        });
+
+        assert_eq!(options.global.proxy.as_deref(), Some("http://command-line"));
+        assert_eq!(options.global.retries, Some(5));
+    }
+
+    #[test]
+    fn moderation_fallbacks() {
+        let options = Options::from_iter(&[
+            "tarmac",
+            "sync",
+            "--target",
+            "roblox",
+            "--moderation-fallbacks",
+            "description,name",
+        ]);
+
+        match options.command {
+            Subcommand::Sync(options) => assert_eq!(
+                options.moderation_fallbacks,
+                [ModerationFallback::Description, ModerationFallback::Name]
+            ),
+            _ => unreachable!(),
+        }
+
+        assert!("title".parse::<ModerationFallback>().is_err());
+    }
+
+    #[test]
+    fn since() {
+        assert_eq!(
+            parse_since("1970-01-02T00:00:00Z"),
+            Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(24 * 60 * 60))
+        );
+
+        let two_hours_ago = parse_since("2h").unwrap();
+        let elapsed = two_hours_ago.elapsed().unwrap();
+        assert!(elapsed >= Duration::from_secs(2 * 60 * 60));
+        assert!(elapsed < Duration::from_secs(2 * 60 * 60 + 60));
+
+        assert!(parse_since("yesterday").is_err());
+    }
+
+    #[test]
+    fn tags() {
+        assert_eq!(parse_tag("ui"), Ok("ui".to_owned()));
+        assert_eq!(parse_tag("v2.1_icons-old"), Ok("v2.1_icons-old".to_owned()));
+        assert!(parse_tag("").is_err());
+        assert!(parse_tag("has space").is_err());
+        assert!(parse_tag("naïve").is_err());
+        assert!(parse_tag(&"a".repeat(MAX_TAG_LENGTH + 1)).is_err());
+
+        let options = Options::from_iter(&["tarmac", "upload-image", "foo.png", "--tags", "ui,v2"]);
+        match options.command {
+            Subcommand::UploadImage(upload) => assert_eq!(upload.settings.tags, ["ui", "v2"]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn creator_ids() {
+        assert_eq!(parse_creator_id("1234567"), Ok(1234567));
+        assert!(parse_creator_id("0").is_err());
+        assert!(parse_creator_id("-5").is_err());
+        assert!(parse_creator_id("group").is_err());
+
+        let result = Options::from_iter_safe(&["tarmac", "list", "--user-id", "0"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn retry_policy_from_config() {
+        let mut options = Options::from_iter(&["tarmac", "--retries", "1", "info", "1"]);
+        options.global.apply_config(OptionsConfig {
+            retry_policy: Some(RetryPolicy::None),
+            ..OptionsConfig::default()
+        });
+
+        assert_eq!(options.global.retries, Some(1));
+        assert_eq!(options.global.retry_policy, None);
+
+        let mut options = Options::from_iter(&["tarmac", "info", "1"]);
+        options.global.apply_config(OptionsConfig {
+            retry_policy: Some(RetryPolicy::None),
+            ..OptionsConfig::default()
+        });
+
+        assert_eq!(options.global.retry_policy, Some(RetryPolicy::None));
+    }
+
+    #[test]
+    fn parse_retry_policy() {
+        assert_eq!("none".parse(), Ok(RetryPolicy::None));
+        assert_eq!(
+            "fixed:5:1000".parse(),
+            Ok(RetryPolicy::Fixed {
+                attempts: 5,
+                delay: Duration::from_millis(1000),
+            })
+        );
+        assert_eq!("exponential:3:250".parse(), Ok(RetryPolicy::default()));
+
+        assert!("none:1".parse::<RetryPolicy>().is_err());
+        assert!("fixed:5".parse::<RetryPolicy>().is_err());
+        assert!("fixed:five:1000".parse::<RetryPolicy>().is_err());
+        assert!("linear:5:1000".parse::<RetryPolicy>().is_err());
+
+        for policy in &["none", "fixed:5:1000", "exponential:3:250"] {
+            assert_eq!(policy.parse::<RetryPolicy>().unwrap().to_string(), *policy);
+        }
+    }
+
+    #[test]
+    fn no_retries() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        assert_eq!(RetryPolicy::None.attempts(), 0);
+        assert_eq!(RetryPolicy::None.delay(0, &mut rng), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn fixed_retry_delay() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let policy = RetryPolicy::Fixed {
+            attempts: 5,
+            delay: Duration::from_millis(1000),
+        };
+
+        assert_eq!(policy.attempts(), 5);
+        for attempt in 0..5 {
+            assert_eq!(policy.delay(attempt, &mut rng), Duration::from_millis(1000));
+        }
+    }
+
+    #[test]
+    fn exponential_retry_delay_doubles() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.attempts(), 3);
+        assert_eq!(policy.max_delay(0), Duration::from_millis(250));
+        assert_eq!(policy.max_delay(1), Duration::from_millis(500));
+        assert_eq!(policy.max_delay(2), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn exponential_retry_delay_jitter() {
+        let policy = RetryPolicy::default();
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let delays: Vec<Duration> = (0..5)
+            .map(|attempt| policy.delay(attempt, &mut rng))
+            .collect();
+
+        for (attempt, delay) in delays.iter().enumerate() {
+            assert!(*delay <= policy.max_delay(attempt));
+        }
+
+        let mut same_seed = StdRng::seed_from_u64(7);
+        let repeated: Vec<Duration> = (0..5)
+            .map(|attempt| policy.delay(attempt, &mut same_seed))
+            .collect();
+
+        assert_eq!(delays, repeated);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let spread: HashSet<Duration> = (0..20).map(|_| policy.delay(4, &mut rng)).collect();
+
+        assert!(spread.len() > 1);
+    }
+
+    #[test]
+    fn debug_redacts_auth() {
+        let options = Options::from_iter(&[
+            "tarmac",
+            "--auth",
+            "_|WARNING:-DO-NOT-SHARE-THIS.secret-cookie",
+            "info",
+            "1",
+        ]);
+
+        assert_eq!(
+            options.global.auth.as_ref().map(Secret::expose),
+            Some("_|WARNING:-DO-NOT-SHARE-THIS.secret-cookie")
+        );
+        assert!(!format!("{:?}", options).contains("secret-cookie"));
+        assert!(!format!("{:#?}", options.global).contains("secret-cookie"));
+    }
+}
@@ -1,21 +1,60 @@
 use std::{
     borrow::Cow,
+    collections::HashSet,
     fmt::{self, Write},
+    io::Cursor,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
 };
 
 use reqwest::{
-    header::{HeaderValue, COOKIE},
-    Client, Request, Response, StatusCode,
+    header::{
+        HeaderMap, HeaderName, HeaderValue, InvalidHeaderValue, COOKIE, RETRY_AFTER, USER_AGENT,
+    },
+    Body, Client, Proxy, Request, Response, StatusCode,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::{
+    auth_cookie::get_auth_cookie,
+    options::{GlobalOptions, ImageAssetType, ModerationFallback, RetryPolicy},
+    secret::Secret,
+};
+
 #[derive(Debug, Clone)]
 pub struct ImageUploadData<'a> {
     pub image_data: Cow<'a, [u8]>,
     pub name: &'a str,
     pub description: &'a str,
     pub group_id: Option<u64>,
+    pub asset_type: ImageAssetType,
+}
+
+/// How Roblox responded to `RobloxApiClient::ping`.
+#[derive(Debug, Clone)]
+pub struct PingResponse {
+    pub url: String,
+    pub status: StatusCode,
+
+    /// How long Roblox took to respond, including connecting to it.
+    pub latency: Duration,
+}
+
+impl PingResponse {
+    /// Roblox responds to the ping with a server error (HTTP 5xx) when it's
+    /// reachable but having problems of its own.
+    pub fn is_healthy(&self) -> bool {
+        !self.status.is_server_error()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AudioUploadData<'a> {
+    pub audio_data: Cow<'a, [u8]>,
+    pub name: &'a str,
+    pub group_id: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -36,10 +75,168 @@ struct RawUploadResponse {
     backing_asset_id: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioUploadResponse {
+    #[serde(rename = "Id")]
+    pub asset_id: u64,
+}
+
+/// Details about an existing asset, as returned by the economy asset details
+/// endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssetInfo {
+    pub asset_id: u64,
+    pub name: String,
+    pub description: Option<String>,
+    pub asset_type_id: u32,
+    pub creator: AssetCreator,
+    pub created: String,
+    pub updated: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct AssetCreator {
+    pub id: u64,
+    pub name: String,
+    pub creator_type: String,
+}
+
+/// An asset in a user's inventory, as returned by the inventory endpoint.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryAsset {
+    pub asset_id: u64,
+    pub name: String,
+    pub asset_type: String,
+}
+
+/// One page of a user's inventory. There are more pages to request if
+/// `next_page_cursor` is set.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InventoryPage {
+    data: Vec<InventoryAsset>,
+    next_page_cursor: Option<String>,
+}
+
+/// The thumbnails of the requested assets, as returned by the thumbnails
+/// endpoint.
+#[derive(Debug, Deserialize)]
+struct Thumbnails {
+    data: Vec<Thumbnail>,
+}
+
+/// An asset's thumbnail, which can only be downloaded once its state is
+/// `Completed`. Thumbnails that are still being generated are `Pending`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Thumbnail {
+    state: String,
+    image_url: Option<String>,
+}
+
+/// The user that the client is authenticated as.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatedUser {
+    pub id: u64,
+    pub name: String,
+    pub display_name: String,
+}
+
+/// The groups that a user is a member of, and their role in each.
+#[derive(Debug, Deserialize)]
+struct GroupMemberships {
+    data: Vec<GroupMembership>,
+}
+
+impl GroupMemberships {
+    fn contains(&self, group_id: u64) -> bool {
+        self.data
+            .iter()
+            .any(|membership| membership.group.id == group_id)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupMembership {
+    pub group: MembershipGroup,
+    pub role: MembershipRole,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MembershipGroup {
+    pub id: u64,
+    pub name: String,
+}
+
+/// A user's role in a group. Higher ranks have more permissions, up to 255 for
+/// the group's owner.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MembershipRole {
+    pub name: String,
+    pub rank: u8,
+}
+
+/// The body of a request to the audio publish endpoint, which expects the file
+/// contents to be encoded as base64 instead of sent as the request body.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAudioUploadRequest<'a> {
+    name: &'a str,
+    file: String,
+    group_id: Option<u64>,
+    payment_source: &'static str,
+}
+
+/// How many times the thumbnail of an asset is requested while Roblox is still
+/// generating it, and how long to wait between requests.
+const THUMBNAIL_POLL_ATTEMPTS: usize = 10;
+const THUMBNAIL_POLL_DELAY: Duration = Duration::from_secs(1);
+
+/// The longest we'll wait for a rate limit to expire by default, regardless of
+/// what the `Retry-After` header asks for.
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The number of seconds a request may take, from connecting to reading the
+/// whole response, unless `--timeout` says otherwise. Uploads of large images
+/// can take a while, so this is generous.
+const DEFAULT_TIMEOUT: u64 = 60;
+
+/// The number of seconds Tarmac will wait to connect to Roblox, unless
+/// `--connect-timeout` says otherwise.
+const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+
+/// Every `.ROBLOSECURITY` cookie that Roblox hands out starts with this.
+const COOKIE_PREFIX: &str = "_|WARNING:-DO-NOT-SHARE-THIS.";
+
+/// The domain that Roblox's production web APIs are hosted under.
+const DEFAULT_API_BASE: &str = "roblox.com";
+
+/// Cloning a client is cheap, and clones share the same connection pool and
+/// creator checks, so a client can be cloned to make requests from several
+/// threads at once.
+#[derive(Clone)]
 pub struct RobloxApiClient {
-    auth_token: Option<String>,
+    auth_token: Option<Secret>,
     csrf_token: Option<HeaderValue>,
     client: Client,
+    api_base: String,
+    extra_headers: HeaderMap,
+    retry_policy: RetryPolicy,
+    max_retry_after: Duration,
+    validate_creator: bool,
+    creator_cache: Arc<Mutex<CreatorCache>>,
+}
+
+/// What a client and its clones have learned from checking the creator of
+/// their uploads, so that each check only happens once.
+#[derive(Debug, Default)]
+struct CreatorCache {
+    user_id: Option<u64>,
+    group_ids: HashSet<u64>,
 }
 
 impl fmt::Debug for RobloxApiClient {
@@ -49,19 +246,192 @@ impl fmt::Debug for RobloxApiClient {
 }
 
 impl RobloxApiClient {
-    pub fn new(auth_token: Option<String>) -> Self {
+    /// Create a client configured by the global options passed to Tarmac.
+    ///
+    /// The authentication cookie is taken from `--auth`, or looked up in a
+    /// Roblox Studio installation if none was given. The lookup only happens
+    /// here, so clones of this client share its result instead of repeating
+    /// it for every upload.
+    pub fn from_options(global: &GlobalOptions) -> Result<Self, RobloxApiError> {
+        Self::from_options_with(global, get_auth_cookie)
+    }
+
+    /// Like `from_options`, but looks the cookie up with `find_cookie` instead
+    /// of in Roblox Studio when `--auth` isn't given.
+    pub(crate) fn from_options_with<F>(
+        global: &GlobalOptions,
+        find_cookie: F,
+    ) -> Result<Self, RobloxApiError>
+    where
+        F: FnOnce() -> Option<Secret>,
+    {
+        let auth_token = global.auth.clone().or_else(|| {
+            let cookie = find_cookie();
+
+            if cookie.is_some() {
+                log::debug!("Using the authentication cookie from Roblox Studio");
+            }
+
+            cookie
+        });
+
+        if let Some(auth_token) = &auth_token {
+            validate_cookie(auth_token.expose())?;
+        }
+
+        let mut client = Self::without_auth(global)?;
+        client.auth_token = auth_token;
+
+        Ok(client)
+    }
+
+    /// Create a client configured by the global options passed to Tarmac, but
+    /// without an authentication cookie, for requests that don't need one.
+    pub fn without_auth(global: &GlobalOptions) -> Result<Self, RobloxApiError> {
+        let mut client = Self::with_client(build_http_client(global)?, None);
+
+        if let Some(api_base) = &global.api_base {
+            client = client.api_base(api_base.clone());
+        }
+
+        let max_retry_after = global
+            .max_retry_after
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_MAX_RETRY_AFTER);
+
+        let retry_policy = match (global.retry_policy, global.retries) {
+            (Some(retry_policy), _) => retry_policy,
+            (None, Some(retries)) => RetryPolicy::exponential(retries),
+            (None, None) => RetryPolicy::default(),
+        };
+
+        Ok(client
+            .extra_headers(parse_extra_headers(&global.extra_headers)?)
+            .retry_policy(retry_policy)
+            .max_retry_after(max_retry_after)
+            .validate_creator(!global.no_validate_creator))
+    }
+
+    /// Create a client that sends its requests through the given
+    /// `reqwest::Client`, which lets callers share one connection pool across
+    /// multiple API clients or configure the HTTP client themselves.
+    pub fn with_client(client: Client, auth_token: Option<Secret>) -> Self {
         Self {
             auth_token,
             csrf_token: None,
-            client: Client::new(),
+            client,
+            api_base: DEFAULT_API_BASE.to_owned(),
+            extra_headers: HeaderMap::new(),
+            retry_policy: RetryPolicy::None,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            validate_creator: false,
+            creator_cache: Arc::default(),
         }
     }
 
+    /// Sets the domain that requests are sent to, which is `roblox.com` by
+    /// default. Each API is reached through its usual subdomain of this domain.
+    pub fn api_base(mut self, api_base: String) -> Self {
+        self.api_base = api_base;
+        self
+    }
+
+    /// Sets headers to send with every request to a Roblox API, on top of the
+    /// headers the client sends itself. Values marked as sensitive are
+    /// redacted when requests are logged.
+    pub fn extra_headers(mut self, extra_headers: HeaderMap) -> Self {
+        self.extra_headers = extra_headers;
+        self
+    }
+
+    /// Sets how requests are retried if they fail because of a network error
+    /// or a server error (HTTP 5xx), and how many times rate limited requests
+    /// are retried. Requests aren't retried by default.
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Sets the longest amount of time the client will wait before retrying a
+    /// request that was rate limited. Longer `Retry-After` values returned by
+    /// Roblox are clamped to this value.
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Sets whether the client checks that the authenticated user can upload
+    /// assets before its first upload, and before the first upload to each
+    /// group. This catches bad cookies and group IDs with a clearer error than
+    /// the upload endpoints give. Off by default.
+    pub fn validate_creator(mut self, validate_creator: bool) -> Self {
+        self.validate_creator = validate_creator;
+        self
+    }
+
     pub fn download_image(&mut self, id: u64) -> Result<Vec<u8>, RobloxApiError> {
-        let url = format!("https://roblox.com/asset?id={}", id);
+        let url = self.api_url(None, &format!("/asset?id={}", id));
 
-        let mut response =
-            self.execute_with_csrf_retry(|client| Ok(client.get(&url).build()?))?;
+        let mut response = self.execute_with_retry(|client| Ok(client.get(&url).build()?))?;
+
+        let mut buffer = Vec::new();
+        response.copy_to(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    /// Downloads the thumbnail of an asset as a PNG of the given size, like
+    /// `420x420`. Roblox generates thumbnails when they're first asked for, so
+    /// this waits for a little while if the thumbnail isn't ready yet.
+    pub fn get_thumbnail(&mut self, asset_id: u64, size: &str) -> Result<Vec<u8>, RobloxApiError> {
+        let url = self.api_url(Some("thumbnails"), "/v1/assets");
+        let asset_ids = asset_id.to_string();
+        let query = [
+            ("assetIds", asset_ids.as_str()),
+            ("size", size),
+            ("format", "Png"),
+            ("isCircular", "false"),
+        ];
+
+        for attempt in 0..THUMBNAIL_POLL_ATTEMPTS {
+            if attempt > 0 {
+                thread::sleep(THUMBNAIL_POLL_DELAY);
+            }
+
+            let thumbnails: Thumbnails = self.get_json_with_query(&url, &query)?;
+            let thumbnail = thumbnails
+                .data
+                .into_iter()
+                .next()
+                .ok_or(RobloxApiError::AssetNotFound { asset_id })?;
+
+            match (thumbnail.state.as_str(), thumbnail.image_url) {
+                ("Completed", Some(image_url)) => return self.download_thumbnail(&image_url),
+                ("Pending", _) => {
+                    log::debug!("Thumbnail of asset {} is still being generated", asset_id);
+                }
+                (state, _) => {
+                    return Err(RobloxApiError::ThumbnailUnavailable {
+                        asset_id,
+                        state: state.to_owned(),
+                    })
+                }
+            }
+        }
+
+        Err(RobloxApiError::ThumbnailPending { asset_id })
+    }
+
+    /// Downloads a generated thumbnail. Thumbnails are served from Roblox's
+    /// CDN rather than its API, so the request is sent without the
+    /// authentication cookie.
+    fn download_thumbnail(&self, image_url: &str) -> Result<Vec<u8>, RobloxApiError> {
+        let mut response = self.client.get(image_url).send()?;
+
+        if !response.status().is_success() {
+            let body = response.text()?;
+            return Err(response_error(response.status(), body));
+        }
 
         let mut buffer = Vec::new();
         response.copy_to(&mut buffer)?;
@@ -69,20 +439,273 @@ impl RobloxApiClient {
         Ok(buffer)
     }
 
-    /// Upload an image, retrying if the asset endpoint determines that the
-    /// asset's name is inappropriate. The asset's name will be replaced with a
-    /// generic known-good string.
+    /// Checks that Roblox can be reached by sending a HEAD request to the API
+    /// base, without authentication or retries, and times how long Roblox
+    /// took to respond. Any response means Roblox was reached, even an error.
+    pub fn ping(&self) -> Result<PingResponse, RobloxApiError> {
+        self.ping_url(&self.api_url(None, "/"))
+    }
+
+    fn ping_url(&self, url: &str) -> Result<PingResponse, RobloxApiError> {
+        let mut request = self.client.head(url).build()?;
+
+        for (name, value) in &self.extra_headers {
+            request.headers_mut().append(name, value.clone());
+        }
+
+        let start = Instant::now();
+        let response = self.execute_logged(request)?;
+
+        Ok(PingResponse {
+            url: url.to_owned(),
+            status: response.status(),
+            latency: start.elapsed(),
+        })
+    }
+
+    /// Uploads can't succeed without an authentication cookie, so fail early
+    /// with an explanation instead of letting Roblox reject them.
+    pub fn require_auth(&self) -> Result<(), RobloxApiError> {
+        match self.auth_token {
+            Some(_) => Ok(()),
+            None => Err(RobloxApiError::NoAuthCookie),
+        }
+    }
+
+    /// Makes sure that the authenticated user can upload assets, and that
+    /// they're a member of the group that assets are being uploaded to, if
+    /// any. Each check only happens once for a client and all of its clones.
+    fn check_creator(&mut self, group_id: Option<u64>) -> Result<(), RobloxApiError> {
+        if !self.validate_creator {
+            return Ok(());
+        }
+
+        // Held while checking, so that clones uploading at the same time wait
+        // for the first check instead of repeating it.
+        let cache = Arc::clone(&self.creator_cache);
+        let mut cache = cache.lock().unwrap();
+
+        let user_id = self.cached_user_id(&mut cache)?;
+
+        if let Some(group_id) = group_id {
+            if !cache.group_ids.contains(&group_id) {
+                let memberships = self.get_group_memberships(user_id)?;
+
+                if !memberships.contains(group_id) {
+                    return Err(RobloxApiError::NotInGroup { group_id });
+                }
+
+                cache.group_ids.insert(group_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The ID of the user that the client is authenticated as, which is only
+    /// looked up once for a client and all of its clones.
+    fn authenticated_user_id(&mut self) -> Result<u64, RobloxApiError> {
+        let cache = Arc::clone(&self.creator_cache);
+        let mut cache = cache.lock().unwrap();
+
+        self.cached_user_id(&mut cache)
+    }
+
+    fn cached_user_id(&mut self, cache: &mut CreatorCache) -> Result<u64, RobloxApiError> {
+        match cache.user_id {
+            Some(user_id) => Ok(user_id),
+            None => {
+                let user_id = self.get_authenticated_user_id()?;
+                log::debug!("Authenticated as user {}", user_id);
+
+                cache.user_id = Some(user_id);
+                Ok(user_id)
+            }
+        }
+    }
+
+    fn get_authenticated_user_id(&mut self) -> Result<u64, RobloxApiError> {
+        Ok(self.get_authenticated_user()?.id)
+    }
+
+    /// Looks up the user that the client is authenticated as, which fails
+    /// with `NotAuthenticated` if Roblox doesn't accept the cookie.
+    pub fn get_authenticated_user(&mut self) -> Result<AuthenticatedUser, RobloxApiError> {
+        let url = self.api_url(Some("users"), "/v1/users/authenticated");
+
+        match self.get_json(&url) {
+            Err(RobloxApiError::Unauthorized { .. }) => Err(RobloxApiError::NotAuthenticated),
+            result => result,
+        }
+    }
+
+    /// Lists the groups that a user is a member of, along with their role in
+    /// each.
+    pub fn get_user_groups(
+        &mut self,
+        user_id: u64,
+    ) -> Result<Vec<GroupMembership>, RobloxApiError> {
+        Ok(self.get_group_memberships(user_id)?.data)
+    }
+
+    fn get_group_memberships(&mut self, user_id: u64) -> Result<GroupMemberships, RobloxApiError> {
+        let url = self.api_url(
+            Some("groups"),
+            &format!("/v1/users/{}/groups/roles", user_id),
+        );
+
+        self.get_json(&url)
+    }
+
+    /// Sends a GET request to the given URL, deserializing the response body
+    /// as JSON if the request succeeds.
+    fn get_json<T: DeserializeOwned>(&mut self, url: &str) -> Result<T, RobloxApiError> {
+        self.get_json_with_query(url, &[])
+    }
+
+    /// Like `get_json`, but adds the given pairs to the URL's query string,
+    /// encoding them as needed.
+    fn get_json_with_query<T: DeserializeOwned>(
+        &mut self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, RobloxApiError> {
+        let mut response =
+            self.execute_with_retry(|client| Ok(client.get(url).query(query).build()?))?;
+
+        let body = response.text()?;
+
+        if response.status().is_success() {
+            parse_response_json(response.url().path(), body)
+        } else {
+            Err(response_error(response.status(), body))
+        }
+    }
+
+    /// Lists the images, decals, and audio in a user's inventory, requesting
+    /// pages until there are none left or `limit` assets have been found.
+    /// Lists the authenticated user's assets if no user is given.
+    pub fn list_assets(
+        &mut self,
+        user_id: Option<u64>,
+        limit: Option<usize>,
+    ) -> Result<Vec<InventoryAsset>, RobloxApiError> {
+        let user_id = match user_id {
+            Some(user_id) => user_id,
+            None => self.authenticated_user_id()?,
+        };
+
+        let url = self.api_url(
+            Some("inventory"),
+            &format!("/v2/users/{}/inventory", user_id),
+        );
+
+        collect_pages(limit, |cursor| {
+            let mut query = vec![
+                ("assetTypes", "Image,Decal,Audio"),
+                ("limit", "100"),
+                ("sortOrder", "Desc"),
+            ];
+
+            if let Some(cursor) = cursor {
+                query.push(("cursor", cursor));
+            }
+
+            self.get_json_with_query(&url, &query)
+        })
+    }
+
+    /// Look up the details of an existing asset, like its name, type, and
+    /// creator.
+    pub fn get_asset_info(&mut self, asset_id: u64) -> Result<AssetInfo, RobloxApiError> {
+        let url = self.api_url(Some("economy"), &format!("/v2/assets/{}/details", asset_id));
+
+        let mut response = self.execute_with_retry(|client| Ok(client.get(&url).build()?))?;
+
+        let body = response.text()?;
+
+        match response.status() {
+            status if status.is_success() => parse_response_json(response.url().path(), body),
+            StatusCode::NOT_FOUND => Err(RobloxApiError::AssetNotFound { asset_id }),
+            status => Err(response_error(status, body)),
+        }
+    }
+
+    /// Upload an image, retrying up to `max_retries` times if the asset
+    /// endpoint determines that the asset is inappropriate. Before each retry,
+    /// the next of `fallbacks` replaces the asset's name or description with a
+    /// generic known-good string, repeating the last one when they run out.
+    ///
+    /// Once the retries are used up, the last `ModerationRejected` error is
+    /// returned.
     pub fn upload_image_with_moderation_retry(
+        &mut self,
+        mut data: ImageUploadData,
+        max_retries: u32,
+        fallbacks: &[ModerationFallback],
+    ) -> Result<UploadResponse, RobloxApiError> {
+        let mut retries = 0;
+
+        loop {
+            match self.upload_image_checked(&data) {
+                Err(RobloxApiError::ModerationRejected { .. }) if retries < max_retries => {
+                    let fallback = fallbacks.get(retries as usize).or_else(|| fallbacks.last());
+                    retries += 1;
+
+                    match fallback {
+                        Some(ModerationFallback::Name) => {
+                            log::warn!(
+                                "Image '{}' was moderated, retrying with a different name...",
+                                data.name
+                            );
+                            data.name = "image";
+                        }
+                        Some(ModerationFallback::Description) => {
+                            log::warn!(
+                                "Image '{}' was moderated, retrying without a description...",
+                                data.name
+                            );
+                            data.description = "";
+                        }
+                        None => log::warn!("Image '{}' was moderated, retrying...", data.name),
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Upload an image, returning an error if anything goes wrong.
+    pub fn upload_image(
         &mut self,
         data: ImageUploadData,
     ) -> Result<UploadResponse, RobloxApiError> {
-        let response = self.upload_image_raw(&data)?;
+        self.upload_image_checked(&data)
+    }
+
+    /// Upload an image, turning any failures reported inside of the response
+    /// into errors.
+    fn upload_image_checked(
+        &mut self,
+        data: &ImageUploadData,
+    ) -> Result<UploadResponse, RobloxApiError> {
+        let response = self.upload_image_raw(data)?;
 
         // Some other errors will be reported inside the response, even
         // though we received a successful HTTP response.
         if response.success {
             let asset_id = response.asset_id.unwrap();
-            let backing_asset_id = response.backing_asset_id.unwrap();
+
+            // Images are their own backing image, so only decals come back
+            // with a separate one.
+            let backing_asset_id = response.backing_asset_id.unwrap_or(asset_id);
+
+            log::debug!(
+                "Uploaded image '{}' as asset {} with image {}",
+                data.name,
+                asset_id,
+                backing_asset_id
+            );
 
             Ok(UploadResponse {
                 asset_id,
@@ -95,47 +718,50 @@ impl RobloxApiClient {
             // on the returned error message.
             //
             // If the error message text mentions something being
-            // inappropriate, we assume the title was problematic and
-            // attempt to re-upload.
+            // inappropriate, we assume the title was problematic.
             if message.contains("inappropriate") {
-                log::warn!(
-                    "Image name '{}' was moderated, retrying with different name...",
-                    data.name
-                );
-
-                let new_data = ImageUploadData {
-                    name: "image",
-                    ..data
-                };
-
-                self.upload_image(new_data)
+                Err(RobloxApiError::ModerationRejected {
+                    asset_name: data.name.to_owned(),
+                    message,
+                })
             } else {
                 Err(RobloxApiError::ApiError { message })
             }
         }
     }
 
-    /// Upload an image, returning an error if anything goes wrong.
-    pub fn upload_image(
+    /// Upload an audio file, returning an error if anything goes wrong. The
+    /// audio data is expected to be an MP3 or OGG file and is sent as-is.
+    pub fn upload_audio(
         &mut self,
-        data: ImageUploadData,
-    ) -> Result<UploadResponse, RobloxApiError> {
-        let response = self.upload_image_raw(&data)?;
+        data: AudioUploadData,
+    ) -> Result<AudioUploadResponse, RobloxApiError> {
+        self.require_auth()?;
+        self.check_creator(data.group_id)?;
 
-        // Some other errors will be reported inside the response, even
-        // though we received a successful HTTP response.
-        if response.success {
-            let asset_id = response.asset_id.unwrap();
-            let backing_asset_id = response.backing_asset_id.unwrap();
+        let url = self.api_url(Some("publish"), "/v1/audio");
 
-            Ok(UploadResponse {
-                asset_id,
-                backing_asset_id,
-            })
-        } else {
-            let message = response.message.unwrap();
+        let payment_source = match data.group_id {
+            Some(_) => "Group",
+            None => "User",
+        };
+
+        let request_body = RawAudioUploadRequest {
+            name: data.name,
+            file: base64::encode(&data.audio_data),
+            group_id: data.group_id,
+            payment_source,
+        };
 
-            Err(RobloxApiError::ApiError { message })
+        let mut response =
+            self.execute_with_retry(|client| Ok(client.post(&url).json(&request_body).build()?))?;
+
+        let body = read_upload_body(&mut response)?;
+
+        if response.status().is_success() {
+            parse_response_json(response.url().path(), body)
+        } else {
+            Err(response_error(response.status(), body))
         }
     }
 
@@ -145,49 +771,152 @@ impl RobloxApiClient {
         &mut self,
         data: &ImageUploadData,
     ) -> Result<RawUploadResponse, RobloxApiError> {
-        let mut url = "https://data.roblox.com/data/upload/json?assetTypeId=13".to_owned();
+        self.require_auth()?;
+        self.check_creator(data.group_id)?;
 
-        if let Some(group_id) = data.group_id {
-            write!(url, "&groupId={}", group_id).unwrap();
-        }
+        let url = self.image_upload_url(data.asset_type, data.group_id);
+
+        // Sending a request consumes its body, so each attempt needs a new
+        // one. They all read from the same copy of the image instead of
+        // copying it again for every attempt.
+        let image_data: Arc<[u8]> = Arc::from(&*data.image_data);
+
+        let mut response = self.execute_with_retry(|client| {
+            let body = Body::sized(
+                Cursor::new(Arc::clone(&image_data)),
+                image_data.len() as u64,
+            );
 
-        let mut response = self.execute_with_csrf_retry(|client| {
             Ok(client
                 .post(&url)
                 .query(&[("name", data.name), ("description", data.description)])
-                .body(data.image_data.clone().into_owned())
+                .body(body)
                 .build()?)
         })?;
 
-        let body = response.text()?;
+        let body = read_upload_body(&mut response)?;
 
         // Some errors will be reported through HTTP status codes, handled here.
         if response.status().is_success() {
-            match serde_json::from_str(&body) {
-                Ok(response) => Ok(response),
-                Err(source) => Err(RobloxApiError::BadResponseJson { body, source }),
-            }
+            parse_response_json(response.url().path(), body)
         } else {
-            Err(RobloxApiError::ResponseError {
-                status: response.status(),
-                body,
-            })
+            Err(response_error(response.status(), body))
         }
     }
 
-    /// Execute a request generated by the given function, retrying if the
-    /// endpoint requests that the user refreshes their CSRF token.
-    fn execute_with_csrf_retry<F>(&mut self, make_request: F) -> Result<Response, RobloxApiError>
+    /// Builds the URL that images of the given asset type are uploaded to.
+    fn image_upload_url(&self, asset_type: ImageAssetType, group_id: Option<u64>) -> String {
+        let path = format!(
+            "/data/upload/json?assetTypeId={}",
+            asset_type.asset_type_id()
+        );
+        let mut url = self.api_url(Some("data"), &path);
+
+        if let Some(group_id) = group_id {
+            write!(url, "&groupId={}", group_id).unwrap();
+        }
+
+        url
+    }
+
+    /// Builds the URL to the given path on one of Roblox's API subdomains.
+    fn api_url(&self, subdomain: Option<&str>, path: &str) -> String {
+        match subdomain {
+            Some(subdomain) => format!("https://{}.{}{}", subdomain, self.api_base, path),
+            None => format!("https://{}{}", self.api_base, path),
+        }
+    }
+
+    /// Execute a request generated by the given function, retrying according
+    /// to the retry policy if the request fails for reasons that are likely to
+    /// be temporary, like a dropped connection, a server error, or being rate
+    /// limited. Other client errors (HTTP 4xx) are never retried.
+    ///
+    /// If Roblox tells us how long to wait with a `Retry-After` header, the
+    /// request is retried after that time has passed.
+    fn execute_with_retry<F>(&mut self, make_request: F) -> Result<Response, RobloxApiError>
     where
         F: Fn(&Client) -> Result<Request, RobloxApiError>,
     {
-        let mut request = make_request(&self.client)?;
-        self.attach_headers(&mut request);
+        let mut attempt = 0;
+        let max_retries = self.retry_policy.attempts();
 
-        let response = self.client.execute(request)?;
+        loop {
+            let result = self.execute_with_csrf_retry(&make_request);
 
-        match response.status() {
-            StatusCode::FORBIDDEN => {
+            let should_retry = match &result {
+                Ok(response) => is_retryable_status(response.status()),
+                Err(err) => err.is_retryable(),
+            };
+
+            if !should_retry || attempt >= max_retries {
+                return match result {
+                    Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                        Err(RobloxApiError::RateLimited {
+                            retry_after: parse_retry_after(response.headers()),
+                        })
+                    }
+                    result => result,
+                };
+            }
+
+            let retry_after = result
+                .as_ref()
+                .ok()
+                .and_then(|response| parse_retry_after(response.headers()))
+                .map(|retry_after| retry_after.min(self.max_retry_after));
+
+            // Workers that were rate limited together would otherwise all
+            // retry at the same moment, so each waits a little longer than
+            // asked, by the delay the retry policy gives, which is usually
+            // random.
+            let delay = retry_after.unwrap_or_default()
+                + self.retry_policy.delay(attempt, &mut rand::thread_rng());
+            attempt += 1;
+
+            match &result {
+                Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => log::info!(
+                    "Rate limited by Roblox, retrying in {}s ({}/{})",
+                    delay.as_secs(),
+                    attempt,
+                    max_retries
+                ),
+                Ok(response) => log::debug!(
+                    "Request failed with {}, retrying in {}ms ({}/{})",
+                    response.status(),
+                    delay.as_millis(),
+                    attempt,
+                    max_retries
+                ),
+                Err(err) => log::debug!(
+                    "Request failed: {}, retrying in {}ms ({}/{})",
+                    err,
+                    delay.as_millis(),
+                    attempt,
+                    max_retries
+                ),
+            }
+
+            thread::sleep(delay);
+        }
+    }
+
+    /// Execute a request generated by the given function, retrying once if the
+    /// endpoint requests that the user refreshes their CSRF token.
+    ///
+    /// The token is kept for later requests, so it's only fetched again once
+    /// Roblox says it has expired.
+    fn execute_with_csrf_retry<F>(&mut self, make_request: F) -> Result<Response, RobloxApiError>
+    where
+        F: Fn(&Client) -> Result<Request, RobloxApiError>,
+    {
+        let mut request = make_request(&self.client)?;
+        self.attach_headers(&mut request);
+
+        let response = self.execute_logged(request)?;
+
+        match response.status() {
+            StatusCode::FORBIDDEN => {
                 if let Some(csrf) = response.headers().get("X-CSRF-Token") {
                     log::debug!("Retrying request with X-CSRF-Token...");
 
@@ -196,7 +925,18 @@ impl RobloxApiClient {
                     let mut new_request = make_request(&self.client)?;
                     self.attach_headers(&mut new_request);
 
-                    Ok(self.client.execute(new_request)?)
+                    let new_response = self.execute_logged(new_request)?;
+
+                    // Roblox asking for yet another token means that it won't
+                    // accept any we send, so retrying again won't help.
+                    if new_response.status() == StatusCode::FORBIDDEN
+                        && new_response.headers().contains_key("X-CSRF-Token")
+                    {
+                        self.csrf_token = None;
+                        return Err(RobloxApiError::CsrfTokenRejected);
+                    }
+
+                    Ok(new_response)
                 } else {
                     // If the response did not return a CSRF token for us to
                     // retry with, this request was likely forbidden for other
@@ -209,11 +949,43 @@ impl RobloxApiClient {
         }
     }
 
+    /// Send a single request, logging it and its response status. Headers are
+    /// only logged at the trace level, with credentials redacted.
+    ///
+    /// If a request that isn't safe to send twice fails after it may have
+    /// reached Roblox, it fails with `ResponseLost`, which is never retried.
+    fn execute_logged(&self, request: Request) -> Result<Response, RobloxApiError> {
+        let method = request.method().clone();
+        let url = request.url().clone();
+
+        log::debug!("{} {}", method, url);
+        log::trace!("Request headers: {}", redact_headers(request.headers()));
+
+        let result = self.client.execute(request);
+
+        match &result {
+            Ok(response) => log::debug!("{} {} returned {}", method, url, response.status()),
+            Err(err) => log::debug!("{} {} failed: {}", method, url, err),
+        }
+
+        result.map_err(|source| {
+            if method.is_idempotent() || never_reached_server(&source) {
+                source.into()
+            } else {
+                RobloxApiError::ResponseLost { source }
+            }
+        })
+    }
+
     /// Attach required headers to a request object before sending it to a
     /// Roblox API, like authentication and CSRF protection.
     fn attach_headers(&self, request: &mut Request) {
+        for (name, value) in &self.extra_headers {
+            request.headers_mut().append(name, value.clone());
+        }
+
         if let Some(auth_token) = &self.auth_token {
-            let cookie_value = format!(".ROBLOSECURITY={}", auth_token);
+            let cookie_value = format!(".ROBLOSECURITY={}", auth_token.expose());
 
             request.headers_mut().insert(
                 COOKIE,
@@ -227,23 +999,1113 @@ impl RobloxApiClient {
     }
 }
 
+/// Something that can upload assets to Roblox. Commands upload through this
+/// trait instead of `RobloxApiClient` directly so that they can be tested
+/// without network access.
+pub trait AssetUploader {
+    fn upload_image(&mut self, data: ImageUploadData) -> Result<UploadResponse, RobloxApiError>;
+
+    fn upload_audio(
+        &mut self,
+        data: AudioUploadData,
+    ) -> Result<AudioUploadResponse, RobloxApiError>;
+
+    /// Downloads an uploaded image as Roblox stored it.
+    fn download_image(&mut self, id: u64) -> Result<Vec<u8>, RobloxApiError>;
+}
+
+impl AssetUploader for RobloxApiClient {
+    fn upload_image(&mut self, data: ImageUploadData) -> Result<UploadResponse, RobloxApiError> {
+        RobloxApiClient::upload_image(self, data)
+    }
+
+    fn upload_audio(
+        &mut self,
+        data: AudioUploadData,
+    ) -> Result<AudioUploadResponse, RobloxApiError> {
+        RobloxApiClient::upload_audio(self, data)
+    }
+
+    fn download_image(&mut self, id: u64) -> Result<Vec<u8>, RobloxApiError> {
+        RobloxApiClient::download_image(self, id)
+    }
+}
+
+/// An `AssetUploader` for tests that records what it was asked to upload and
+/// hands out sequential asset IDs instead of uploading anything. Downloads
+/// return what was uploaded, unless `corrupt_downloads` is set.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MockUploader {
+    pub uploads: Vec<MockUpload>,
+    pub corrupt_downloads: bool,
+}
+
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct MockUpload {
+    pub name: String,
+    pub contents: Vec<u8>,
+    pub group_id: Option<u64>,
+}
+
+#[cfg(test)]
+impl MockUploader {
+    fn record(&mut self, name: &str, contents: &[u8], group_id: Option<u64>) -> u64 {
+        self.uploads.push(MockUpload {
+            name: name.to_owned(),
+            contents: contents.to_vec(),
+            group_id,
+        });
+
+        self.uploads.len() as u64
+    }
+}
+
+#[cfg(test)]
+impl AssetUploader for MockUploader {
+    fn upload_image(&mut self, data: ImageUploadData) -> Result<UploadResponse, RobloxApiError> {
+        let id = self.record(data.name, &data.image_data, data.group_id);
+
+        Ok(UploadResponse {
+            asset_id: id,
+            backing_asset_id: id,
+        })
+    }
+
+    fn upload_audio(
+        &mut self,
+        data: AudioUploadData,
+    ) -> Result<AudioUploadResponse, RobloxApiError> {
+        let id = self.record(data.name, &data.audio_data, data.group_id);
+
+        Ok(AudioUploadResponse { asset_id: id })
+    }
+
+    fn download_image(&mut self, id: u64) -> Result<Vec<u8>, RobloxApiError> {
+        if self.corrupt_downloads {
+            return Ok(b"not an image".to_vec());
+        }
+
+        let upload = id
+            .checked_sub(1)
+            .and_then(|index| self.uploads.get(index as usize))
+            .ok_or(RobloxApiError::AssetNotFound { asset_id: id })?;
+
+        Ok(upload.contents.clone())
+    }
+}
+
+/// Builds the HTTP client used to talk to Roblox, configured by the global
+/// options passed to Tarmac.
+fn build_http_client(global: &GlobalOptions) -> Result<Client, RobloxApiError> {
+    let user_agent = match &global.user_agent {
+        Some(user_agent) => user_agent.clone(),
+        None => default_user_agent(),
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&user_agent)
+            .map_err(|source| RobloxApiError::InvalidUserAgent { user_agent, source })?,
+    );
+
+    let timeout = global.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    let connect_timeout = global.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+
+    // Roblox may compress its responses, which the client has to decompress
+    // before their bodies can be read as text.
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        .gzip(true)
+        .timeout(Duration::from_secs(timeout))
+        .connect_timeout(Duration::from_secs(connect_timeout));
+
+    if let Some(url) = &global.proxy {
+        let proxy = Proxy::all(url.as_str()).map_err(|source| RobloxApiError::InvalidProxy {
+            url: url.clone(),
+            source,
+        })?;
+
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// The User-Agent sent with every request unless it's overridden, which
+/// identifies the version of Tarmac making the request.
+fn default_user_agent() -> String {
+    format!("tarmac/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Validates the headers given with `--header`. Values of headers whose names
+/// suggest that they carry credentials are marked as sensitive, so that
+/// they're redacted from logs.
+fn parse_extra_headers(headers: &[(String, String)]) -> Result<HeaderMap, RobloxApiError> {
+    let mut header_map = HeaderMap::new();
+
+    for (name, value) in headers {
+        let invalid = |source: Box<dyn std::error::Error + Send + Sync>| RobloxApiError::Headers {
+            name: name.clone(),
+            source,
+        };
+
+        let header_name =
+            HeaderName::from_bytes(name.as_bytes()).map_err(|err| invalid(Box::new(err)))?;
+        let mut header_value =
+            HeaderValue::from_str(value).map_err(|err| invalid(Box::new(err)))?;
+
+        if is_sensitive_header(&header_name) {
+            header_value.set_sensitive(true);
+        }
+
+        header_map.append(header_name, header_value);
+    }
+
+    Ok(header_map)
+}
+
+/// Tells whether a header is likely to carry credentials, judging by its name.
+fn is_sensitive_header(name: &HeaderName) -> bool {
+    const SENSITIVE_WORDS: &[&str] = &[
+        "auth", "cookie", "key", "password", "secret", "session", "token",
+    ];
+
+    SENSITIVE_WORDS
+        .iter()
+        .any(|word| name.as_str().contains(word))
+}
+
+/// Formats a set of headers for logging, replacing the values of any headers
+/// that carry credentials.
+fn redact_headers(headers: &HeaderMap) -> String {
+    let mut output = String::new();
+
+    for (name, value) in headers {
+        if !output.is_empty() {
+            output.push_str(", ");
+        }
+
+        let value = if name == COOKIE || name == "x-csrf-token" || value.is_sensitive() {
+            "<redacted>"
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+
+        write!(output, "{}: {}", name, value).unwrap();
+    }
+
+    output
+}
+
+/// Gathers the assets from each page that `fetch_page` returns, passing it the
+/// cursor of the next page until there are no more pages or `limit` assets
+/// have been gathered.
+fn collect_pages<F>(
+    limit: Option<usize>,
+    mut fetch_page: F,
+) -> Result<Vec<InventoryAsset>, RobloxApiError>
+where
+    F: FnMut(Option<&str>) -> Result<InventoryPage, RobloxApiError>,
+{
+    let mut assets = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let page = fetch_page(cursor.as_deref())?;
+        assets.extend(page.data);
+
+        if let Some(limit) = limit {
+            if assets.len() >= limit {
+                assets.truncate(limit);
+                break;
+            }
+        }
+
+        match page.next_page_cursor {
+            Some(next) if !next.is_empty() => cursor = Some(next),
+            _ => break,
+        }
+    }
+
+    Ok(assets)
+}
+
+/// Turns an unsuccessful response into an error, singling out responses that
+/// mean the authentication cookie was rejected.
+fn response_error(status: StatusCode, body: String) -> RobloxApiError {
+    if status == StatusCode::UNAUTHORIZED {
+        RobloxApiError::Unauthorized { body }
+    } else {
+        let (code, message) = match parse_error_body(&body) {
+            Some(error) => (error.code, Some(error.message)),
+            None => (None, None),
+        };
+
+        RobloxApiError::ResponseError {
+            status,
+            code,
+            message,
+            body,
+        }
+    }
+}
+
+/// An error described in the body of an unsuccessful response.
+#[derive(Debug, PartialEq, Eq)]
+struct ErrorBody {
+    code: Option<String>,
+    message: String,
+}
+
+/// Makes a best-effort attempt at reading the error in the body of an
+/// unsuccessful response. Newer APIs return a single error like
+/// `{"code": "INVALID_ARGUMENT", "message": "..."}`, while older ones return a
+/// list like `{"errors": [{"code": 0, "message": "..."}]}`, of which only the
+/// first is kept. Codes may be strings or numbers.
+fn parse_error_body(body: &str) -> Option<ErrorBody> {
+    #[derive(Deserialize)]
+    struct RawError {
+        code: Option<serde_json::Value>,
+        message: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawErrorBody {
+        List { errors: Vec<RawError> },
+        Single(RawError),
+    }
+
+    let error = match serde_json::from_str(body).ok()? {
+        RawErrorBody::List { errors } => errors.into_iter().next()?,
+        RawErrorBody::Single(error) => error,
+    };
+
+    let code = match error.code {
+        Some(serde_json::Value::String(code)) => Some(code),
+        Some(serde_json::Value::Number(code)) => Some(code.to_string()),
+        _ => None,
+    };
+
+    Some(ErrorBody {
+        code: code.filter(|code| !code.is_empty()),
+        message: error.message,
+    })
+}
+
+/// Describes an unsuccessful response, using the error in its body if it
+/// could be read, and the whole body otherwise.
+fn describe_response_error(
+    status: &StatusCode,
+    code: &Option<String>,
+    message: &Option<String>,
+    body: &str,
+) -> String {
+    match (code, message) {
+        (Some(code), Some(message)) => {
+            format!("Roblox API error [{}]: {} (HTTP {})", code, message, status)
+        }
+        (None, Some(message)) => format!("Roblox API error: {} (HTTP {})", message, status),
+        _ => format!("Roblox API returned HTTP {} with body: {}", status, body),
+    }
+}
+
+/// Deserializes the body of a successful response. If it's malformed, the
+/// error names the endpoint that returned it, since several requests can be
+/// made for a single upload.
+fn parse_response_json<T: DeserializeOwned>(
+    endpoint: &str,
+    body: String,
+) -> Result<T, RobloxApiError> {
+    serde_json::from_str(&body).map_err(|source| RobloxApiError::BadResponseJson {
+        endpoint: endpoint.to_owned(),
+        body,
+        source,
+    })
+}
+
+/// Checks that an authentication cookie looks like one Roblox would accept, so
+/// that a badly pasted cookie gets a better error than a 401 from Roblox. The
+/// cookie itself is never included in the error.
+fn validate_cookie(cookie: &str) -> Result<(), RobloxApiError> {
+    if cookie.trim().is_empty() {
+        return Err(RobloxApiError::InvalidCookie {
+            reason: "it's empty",
+        });
+    }
+
+    if cookie.starts_with(".ROBLOSECURITY=") {
+        return Err(RobloxApiError::InvalidCookie {
+            reason: "it includes the cookie's name, '.ROBLOSECURITY='",
+        });
+    }
+
+    if !cookie.starts_with(COOKIE_PREFIX) {
+        return Err(RobloxApiError::InvalidCookie {
+            reason: "it doesn't start with Roblox's '_|WARNING:-DO-NOT-SHARE-THIS.' warning",
+        });
+    }
+
+    Ok(())
+}
+
+/// Tells whether a request that failed with the given error might succeed if
+/// it's sent again. Timeouts are reported separately, and always retried.
+fn is_transient(error: &reqwest::Error) -> bool {
+    error.is_http()
+}
+
+/// Tells whether a request that failed with the given error is known not to
+/// have reached the server, because a connection to it couldn't be made.
+fn never_reached_server(error: &reqwest::Error) -> bool {
+    error
+        .get_ref()
+        .and_then(|inner| inner.downcast_ref::<hyper::Error>())
+        .is_some_and(hyper::Error::is_connect)
+}
+
+/// Reads the body of the response to an upload. Roblox has handled the upload
+/// by the time it responds, so failing to read the response doesn't mean the
+/// upload failed.
+fn read_upload_body(response: &mut Response) -> Result<String, RobloxApiError> {
+    response
+        .text()
+        .map_err(|source| RobloxApiError::ResponseLost { source })
+}
+
+/// Server errors (HTTP 5xx) and rate limiting are usually temporary, unlike
+/// other client errors.
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Reads the number of seconds that a rate limited request asks us to wait
+/// before trying again.
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+    let seconds = value.trim().parse().ok()?;
+
+    Some(Duration::from_secs(seconds))
+}
+
 #[derive(Debug, Error)]
 pub enum RobloxApiError {
     #[error("Roblox API HTTP error")]
-    Http {
-        #[from]
-        source: reqwest::Error,
+    Http { source: reqwest::Error },
+
+    #[error(
+        "Roblox rejected the CSRF token it gave Tarmac. \
+         The authentication cookie may have expired or been invalidated."
+    )]
+    CsrfTokenRejected,
+
+    #[error("Request to Roblox timed out")]
+    Timeout { source: reqwest::Error },
+
+    #[error(
+        "The connection to Roblox was lost after it may have received the upload, so it wasn't \
+         retried in case that would create a duplicate asset. Check whether the asset was created \
+         before uploading it again."
+    )]
+    ResponseLost { source: reqwest::Error },
+
+    #[error("Invalid proxy URL '{url}'")]
+    InvalidProxy { url: String, source: reqwest::Error },
+
+    #[error("Invalid header '{name}' given with --header")]
+    Headers {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
     },
 
+    #[error("Invalid user agent '{user_agent}'")]
+    InvalidUserAgent {
+        user_agent: String,
+        source: InvalidHeaderValue,
+    },
+
+    #[error("Roblox API rate limited the request")]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error(
+        "Roblox moderated the asset '{asset_name}' ({message}). Try uploading it with a different name or description."
+    )]
+    ModerationRejected { asset_name: String, message: String },
+
+    #[error(
+        "No authentication cookie was given and none could be found in a Roblox Studio installation. \
+         Pass one with --auth or the TARMAC_AUTH environment variable."
+    )]
+    NoAuthCookie,
+
+    #[error(
+        "The authentication cookie is invalid because {reason}. \
+         Make sure to pass only the whole value of the .ROBLOSECURITY cookie."
+    )]
+    InvalidCookie { reason: &'static str },
+
+    #[error(
+        "Roblox did not accept the authentication cookie. \
+         Check that it's valid, or pass --no-validate-creator to skip this check."
+    )]
+    NotAuthenticated,
+
+    #[error(
+        "The authenticated user is not a member of group {group_id}, so they can't upload assets to it. \
+         Check the group ID, or pass --no-validate-creator to skip this check."
+    )]
+    NotInGroup { group_id: u64 },
+
+    #[error("Asset {asset_id} does not exist")]
+    AssetNotFound { asset_id: u64 },
+
+    #[error("Roblox is still generating the thumbnail of asset {asset_id}. Try again shortly.")]
+    ThumbnailPending { asset_id: u64 },
+
+    #[error("The thumbnail of asset {asset_id} isn't available, its state is {state}")]
+    ThumbnailUnavailable { asset_id: u64, state: String },
+
     #[error("Roblox API error: {message}")]
     ApiError { message: String },
 
-    #[error("Roblox API returned success, but had malformed JSON response: {body}")]
+    #[error("Roblox API returned success, but {endpoint} had malformed JSON response: {body}")]
     BadResponseJson {
+        endpoint: String,
         body: String,
         source: serde_json::Error,
     },
 
-    #[error("Roblox API returned HTTP {status} with body: {body}")]
-    ResponseError { status: StatusCode, body: String },
+    #[error(
+        "Roblox rejected the authentication cookie with HTTP 401, so it has probably expired. \
+         Log in to Roblox Studio again, or pass a new cookie with --auth. Response body: {body}"
+    )]
+    Unauthorized { body: String },
+
+    #[error("{}", describe_response_error(.status, .code, .message, .body))]
+    ResponseError {
+        status: StatusCode,
+
+        /// The error code given in the body of the response, if there was
+        /// one.
+        code: Option<String>,
+
+        /// The error message given in the body of the response, if it could
+        /// be read.
+        message: Option<String>,
+        body: String,
+    },
+}
+
+impl RobloxApiError {
+    /// Tells whether the request that failed with this error might succeed if
+    /// it's sent again, like after a dropped connection, a timeout, a server
+    /// error, or being rate limited. Anything wrong with the request itself,
+    /// the cookie, or the asset won't fix itself, so it isn't worth retrying.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            RobloxApiError::Http { source } => is_transient(source),
+            RobloxApiError::Timeout { .. } | RobloxApiError::RateLimited { .. } => true,
+            RobloxApiError::ResponseError { status, .. } => is_retryable_status(*status),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for RobloxApiError {
+    fn from(source: reqwest::Error) -> Self {
+        if source.is_timeout() {
+            RobloxApiError::Timeout { source }
+        } else {
+            RobloxApiError::Http { source }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn retryable_errors() {
+        let response = |status| response_error(status, String::new());
+        assert!(response(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(response(StatusCode::SERVICE_UNAVAILABLE).is_retryable());
+        assert!(!response(StatusCode::BAD_REQUEST).is_retryable());
+        assert!(!response(StatusCode::NOT_FOUND).is_retryable());
+
+        assert!(RobloxApiError::RateLimited { retry_after: None }.is_retryable());
+
+        // Nothing listens on a port that was just released, so connecting to
+        // it fails the way a dropped connection does.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let refused = Client::new()
+            .get(&format!("http://127.0.0.1:{}/", port))
+            .send()
+            .unwrap_err();
+        assert!(RobloxApiError::from(refused).is_retryable());
+
+        let malformed_url = Client::new().get("not a url").build().unwrap_err();
+        assert!(!RobloxApiError::from(malformed_url).is_retryable());
+
+        let not_retryable = [
+            RobloxApiError::BadResponseJson {
+                endpoint: "/v1/assets".to_owned(),
+                body: "{".to_owned(),
+                source: serde_json::from_str::<()>("{").unwrap_err(),
+            },
+            RobloxApiError::ModerationRejected {
+                asset_name: "foo".to_owned(),
+                message: "inappropriate".to_owned(),
+            },
+            RobloxApiError::Unauthorized {
+                body: String::new(),
+            },
+            RobloxApiError::CsrfTokenRejected,
+            RobloxApiError::NoAuthCookie,
+            RobloxApiError::InvalidCookie {
+                reason: "it's empty",
+            },
+            RobloxApiError::NotAuthenticated,
+            RobloxApiError::NotInGroup { group_id: 1 },
+            RobloxApiError::AssetNotFound { asset_id: 1 },
+            RobloxApiError::ThumbnailPending { asset_id: 1 },
+            RobloxApiError::ApiError {
+                message: String::new(),
+            },
+        ];
+
+        for error in &not_retryable {
+            assert!(!error.is_retryable(), "{:?} shouldn't be retried", error);
+        }
+    }
+
+    #[test]
+    fn lost_upload_response_not_retried() {
+        use std::{io::Read, net::TcpListener};
+
+        // Accepts connections and reads each request, but hangs up without
+        // answering, like a connection dropped while Roblox handles a request.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+            }
+        });
+
+        let client = RobloxApiClient::with_client(Client::new(), None);
+
+        let post = client.execute_logged(client.client.post(&url).build().unwrap());
+        match post {
+            Err(error @ RobloxApiError::ResponseLost { .. }) => assert!(!error.is_retryable()),
+            other => panic!("expected ResponseLost, got {:?}", other.map(|_| ())),
+        }
+
+        let get = client.execute_logged(client.client.get(&url).build().unwrap());
+        assert!(get.unwrap_err().is_retryable());
+
+        server.join().unwrap();
+
+        // Nothing listens on the port anymore, so the upload never reached it
+        // and is safe to send again.
+        let refused = client.execute_logged(client.client.post(&url).build().unwrap());
+        assert!(refused.unwrap_err().is_retryable());
+    }
+
+    #[test]
+    fn ping_without_auth() {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        let client = RobloxApiClient::with_client(
+            Client::new(),
+            Some(Secret::new("_|WARNING:-DO-NOT-SHARE-THIS.abc".to_owned())),
+        );
+
+        let response = client.ping_url(&url).unwrap();
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!response.is_healthy());
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("HEAD / "));
+        assert!(!request.to_lowercase().contains("cookie"));
+
+        // Nothing listens on the port anymore.
+        assert!(matches!(
+            client.ping_url(&url),
+            Err(RobloxApiError::Http { .. })
+        ));
+    }
+
+    #[test]
+    fn clones_share_creator_checks() {
+        let mut client = RobloxApiClient::with_client(Client::new(), None).validate_creator(true);
+        let mut clone = client.clone();
+
+        {
+            let mut cache = client.creator_cache.lock().unwrap();
+            cache.user_id = Some(42);
+            cache.group_ids.insert(7);
+        }
+
+        // Both would need to reach Roblox if the checks weren't shared.
+        assert_eq!(clone.authenticated_user_id().unwrap(), 42);
+        assert!(clone.check_creator(Some(7)).is_ok());
+        assert!(client.check_creator(Some(7)).is_ok());
+    }
+
+    #[test]
+    fn retry_rate_limit_without_retry_after() {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        // Rate limits the first two requests without saying how long to wait.
+        let server = thread::spawn(move || {
+            for index in 0..3 {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+
+                let response: &[u8] = if index < 2 {
+                    b"HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                };
+                stream.write_all(response).unwrap();
+            }
+        });
+
+        let policy = RetryPolicy::Fixed {
+            attempts: 1,
+            delay: Duration::from_millis(1),
+        };
+        let request = |client: &Client| Ok(client.get(&url).build()?);
+
+        let mut client = RobloxApiClient::with_client(Client::new(), None).retry_policy(policy);
+        assert!(matches!(
+            client.execute_with_retry(request),
+            Err(RobloxApiError::RateLimited { retry_after: None })
+        ));
+
+        let response = client.execute_with_retry(request).unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn unauthorized_response() {
+        let error = response_error(StatusCode::UNAUTHORIZED, "denied".to_owned());
+        assert!(matches!(error, RobloxApiError::Unauthorized { body } if body == "denied"));
+
+        let error = response_error(StatusCode::BAD_REQUEST, "bad".to_owned());
+        assert!(matches!(
+            error,
+            RobloxApiError::ResponseError {
+                status: StatusCode::BAD_REQUEST,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn response_error_messages() {
+        let error = response_error(
+            StatusCode::BAD_REQUEST,
+            r#"{"code": "INVALID_ARGUMENT", "message": "assetName too long"}"#.to_owned(),
+        );
+        assert!(matches!(
+            &error,
+            RobloxApiError::ResponseError { code: Some(code), .. } if code == "INVALID_ARGUMENT"
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Roblox API error [INVALID_ARGUMENT]: assetName too long (HTTP 400 Bad Request)"
+        );
+
+        let error = response_error(
+            StatusCode::FORBIDDEN,
+            r#"{"errors": [{"code": 3, "message": "Not allowed"}, {"code": 4, "message": "Other"}]}"#
+                .to_owned(),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Roblox API error [3]: Not allowed (HTTP 403 Forbidden)"
+        );
+
+        let error = response_error(
+            StatusCode::BAD_REQUEST,
+            r#"{"message": "Bad name"}"#.to_owned(),
+        );
+        assert_eq!(
+            error.to_string(),
+            "Roblox API error: Bad name (HTTP 400 Bad Request)"
+        );
+
+        let error = response_error(StatusCode::BAD_GATEWAY, "<html>".to_owned());
+        assert!(matches!(
+            &error,
+            RobloxApiError::ResponseError {
+                code: None,
+                message: None,
+                ..
+            }
+        ));
+        assert_eq!(
+            error.to_string(),
+            "Roblox API returned HTTP 502 Bad Gateway with body: <html>"
+        );
+
+        assert_eq!(parse_error_body(r#"{"errors": []}"#), None);
+    }
+
+    #[test]
+    fn malformed_json_names_endpoint() {
+        let error = parse_response_json::<AssetInfo>("/v2/assets/1/details", "<html>".to_owned())
+            .unwrap_err();
+
+        assert!(matches!(
+            &error,
+            RobloxApiError::BadResponseJson { endpoint, body, .. }
+                if endpoint == "/v2/assets/1/details" && body == "<html>"
+        ));
+        assert!(error.to_string().contains("/v2/assets/1/details"));
+    }
+
+    #[test]
+    fn cookie_validation() {
+        assert!(validate_cookie(
+            "_|WARNING:-DO-NOT-SHARE-THIS.--Sharing-this-will-allow-someone-to-log-in-as-you|_ABC"
+        )
+        .is_ok());
+
+        assert!(validate_cookie("").is_err());
+        assert!(validate_cookie("  ").is_err());
+        assert!(validate_cookie("ABC123").is_err());
+        assert!(validate_cookie(".ROBLOSECURITY=_|WARNING:-DO-NOT-SHARE-THIS.ABC").is_err());
+    }
+
+    #[test]
+    fn api_url_default_base() {
+        let client = RobloxApiClient::with_client(Client::new(), None);
+
+        assert_eq!(
+            client.api_url(Some("data"), "/data/upload/json"),
+            "https://data.roblox.com/data/upload/json"
+        );
+        assert_eq!(
+            client.api_url(None, "/asset?id=5"),
+            "https://roblox.com/asset?id=5"
+        );
+    }
+
+    #[test]
+    fn image_upload_url_asset_type() {
+        let client = RobloxApiClient::with_client(Client::new(), None);
+
+        assert_eq!(
+            client.image_upload_url(ImageAssetType::Decal, None),
+            "https://data.roblox.com/data/upload/json?assetTypeId=13"
+        );
+        assert_eq!(
+            client.image_upload_url(ImageAssetType::Image, Some(5)),
+            "https://data.roblox.com/data/upload/json?assetTypeId=1&groupId=5"
+        );
+    }
+
+    #[test]
+    fn api_url_custom_base() {
+        let client = RobloxApiClient::with_client(Client::new(), None)
+            .api_base("sitetest1.robloxlabs.com".to_owned());
+
+        assert_eq!(
+            client.api_url(Some("publish"), "/v1/audio"),
+            "https://publish.sitetest1.robloxlabs.com/v1/audio"
+        );
+    }
+
+    #[test]
+    fn deserialize_asset_info() {
+        let body = r#"{
+            "AssetId": 123,
+            "ProductId": 456,
+            "Name": "Foo",
+            "Description": "Uploaded by Tarmac.",
+            "AssetTypeId": 13,
+            "Creator": { "Id": 7, "Name": "Builderman", "CreatorType": "User" },
+            "Created": "2020-01-01T00:00:00Z",
+            "Updated": "2020-01-02T00:00:00Z"
+        }"#;
+
+        let info: AssetInfo = serde_json::from_str(body).unwrap();
+
+        assert_eq!(info.asset_id, 123);
+        assert_eq!(info.asset_type_id, 13);
+        assert_eq!(info.creator.creator_type, "User");
+    }
+
+    #[test]
+    fn group_membership() {
+        let body = r#"{
+            "data": [
+                {
+                    "group": { "id": 100, "name": "Foo", "memberCount": 3 },
+                    "role": { "id": 1, "name": "Owner", "rank": 255 }
+                }
+            ]
+        }"#;
+
+        let memberships: GroupMemberships = serde_json::from_str(body).unwrap();
+
+        assert!(memberships.contains(100));
+        assert!(!memberships.contains(200));
+        assert_eq!(memberships.data[0].group.name, "Foo");
+        assert_eq!(memberships.data[0].role.rank, 255);
+    }
+
+    fn inventory_page(ids: &[u64], next_page_cursor: Option<&str>) -> InventoryPage {
+        InventoryPage {
+            data: ids
+                .iter()
+                .map(|&asset_id| InventoryAsset {
+                    asset_id,
+                    name: format!("asset-{}", asset_id),
+                    asset_type: "Decal".to_owned(),
+                })
+                .collect(),
+            next_page_cursor: next_page_cursor.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn collect_every_page() {
+        let mut cursors = Vec::new();
+
+        let assets = collect_pages(None, |cursor| {
+            cursors.push(cursor.map(ToOwned::to_owned));
+
+            Ok(match cursor {
+                None => inventory_page(&[1, 2], Some("a")),
+                Some("a") => inventory_page(&[3], Some("b")),
+                _ => inventory_page(&[4], None),
+            })
+        })
+        .unwrap();
+
+        let ids: Vec<u64> = assets.iter().map(|asset| asset.asset_id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(
+            cursors,
+            vec![None, Some("a".to_owned()), Some("b".to_owned())]
+        );
+    }
+
+    #[test]
+    fn collect_pages_up_to_limit() {
+        let mut requests = 0;
+
+        let assets = collect_pages(Some(3), |_| {
+            requests += 1;
+            Ok(inventory_page(&[1, 2], Some("more")))
+        })
+        .unwrap();
+
+        assert_eq!(assets.len(), 3);
+        assert_eq!(requests, 2);
+    }
+
+    #[test]
+    fn deserialize_inventory_page() {
+        let body = r#"{
+            "previousPageCursor": null,
+            "nextPageCursor": "abc",
+            "data": [
+                { "assetId": 5, "name": "Foo", "assetType": "Decal", "created": "2020-01-01T00:00:00Z" }
+            ]
+        }"#;
+
+        let page: InventoryPage = serde_json::from_str(body).unwrap();
+        assert_eq!(page.next_page_cursor.as_deref(), Some("abc"));
+        assert_eq!(page.data[0].asset_id, 5);
+        assert_eq!(page.data[0].name, "Foo");
+    }
+
+    #[test]
+    fn decompress_gzip_response() {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+        };
+
+        use flate2::{write::GzEncoder, Compression};
+        use structopt::StructOpt;
+
+        use crate::options::Options;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(br#"{"id": 42, "name": "builderman", "displayName": "Builderman"}"#)
+                .unwrap();
+            let body = encoder.finish().unwrap();
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                 Content-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        });
+
+        let global = Options::from_iter(&["tarmac", "info", "1"]).global;
+        let mut client = RobloxApiClient::with_client(build_http_client(&global).unwrap(), None);
+
+        let user: AuthenticatedUser = client.get_json(&format!("http://{}/", address)).unwrap();
+        assert_eq!(user.id, 42);
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn deserialize_thumbnails() {
+        let body = r#"{
+            "data": [
+                {
+                    "targetId": 123,
+                    "state": "Completed",
+                    "imageUrl": "https://tr.rbxcdn.com/abc/420/420/Image/Png"
+                }
+            ]
+        }"#;
+
+        let thumbnails: Thumbnails = serde_json::from_str(body).unwrap();
+        assert_eq!(thumbnails.data[0].state, "Completed");
+        assert_eq!(
+            thumbnails.data[0].image_url.as_deref(),
+            Some("https://tr.rbxcdn.com/abc/420/420/Image/Png")
+        );
+
+        let body = r#"{ "data": [{ "targetId": 123, "state": "Pending", "imageUrl": null }] }"#;
+        let thumbnails: Thumbnails = serde_json::from_str(body).unwrap();
+        assert_eq!(thumbnails.data[0].image_url, None);
+    }
+
+    #[test]
+    fn redact_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static(".ROBLOSECURITY=secret"));
+        headers.insert("X-CSRF-Token", HeaderValue::from_static("abc123"));
+        headers.insert(USER_AGENT, HeaderValue::from_static("tarmac"));
+
+        let redacted = redact_headers(&headers);
+
+        assert!(!redacted.contains("secret"));
+        assert!(!redacted.contains("abc123"));
+        assert!(redacted.contains("user-agent: tarmac"));
+    }
+
+    #[test]
+    fn parse_headers() {
+        let headers = parse_extra_headers(&[
+            ("X-Gateway-Token".to_owned(), "hunter2".to_owned()),
+            ("X-Team".to_owned(), "ui".to_owned()),
+            ("X-Team".to_owned(), "build".to_owned()),
+        ])
+        .unwrap();
+
+        assert_eq!(headers.get_all("x-team").iter().count(), 2);
+
+        let redacted = redact_headers(&headers);
+        assert!(!redacted.contains("hunter2"));
+        assert!(redacted.contains("x-team: ui"));
+
+        let bad_name = parse_extra_headers(&[("Bad Name".to_owned(), "value".to_owned())]);
+        assert!(
+            matches!(bad_name, Err(RobloxApiError::Headers { name, .. }) if name == "Bad Name")
+        );
+
+        let bad_value = parse_extra_headers(&[("X-Line".to_owned(), "a\nb".to_owned())]);
+        assert!(matches!(bad_value, Err(RobloxApiError::Headers { .. })));
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("30"));
+
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn retry_after_missing_or_invalid() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), None);
+    }
 }
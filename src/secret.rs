@@ -0,0 +1,53 @@
+//! A wrapper for credentials like the authentication cookie, so that they
+//! can't end up in logs or error messages by accident.
+
+use std::{convert::Infallible, fmt, str::FromStr};
+
+/// A string that isn't shown when formatted with `Debug` or `Display`. The
+/// value has to be asked for explicitly with `expose`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self(value.to_owned()))
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "[REDACTED]")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "[REDACTED]")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formatting_redacts() {
+        let secret = Secret::new("hunter2".to_owned());
+
+        assert_eq!(format!("{:?}", secret), "[REDACTED]");
+        assert_eq!(format!("{}", secret), "[REDACTED]");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+}
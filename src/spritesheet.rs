@@ -0,0 +1,153 @@
+//! Packs many small images together into spritesheets, so that they can be
+//! uploaded as a handful of large images instead of one asset each.
+
+use std::collections::BTreeMap;
+
+use image::{imageops, DynamicImage, GenericImageView};
+use packos::{InputItem, SimplePacker};
+use thiserror::Error;
+
+use crate::data::ImageSlice;
+
+/// One packed image, along with where each sprite ended up within it.
+pub struct Spritesheet {
+    pub image: DynamicImage,
+    pub slices: BTreeMap<String, ImageSlice>,
+}
+
+/// Packs the given named images into as few spritesheets as possible, none of
+/// which are larger than `max_size`. Sprites are separated by a pixel of
+/// padding so that they don't bleed into each other when they're scaled.
+pub fn pack_spritesheets(
+    sprites: &BTreeMap<String, DynamicImage>,
+    max_size: (u32, u32),
+) -> Result<Vec<Spritesheet>, PackError> {
+    let mut inputs = Vec::new();
+    let mut sprites_by_id = Vec::new();
+
+    for (name, image) in sprites {
+        let (width, height) = image.dimensions();
+
+        // The packer can never place a sprite that's larger than a sheet, so
+        // we need to catch those before packing.
+        if width + 1 > max_size.0 || height + 1 > max_size.1 {
+            return Err(PackError::TooLarge {
+                name: name.clone(),
+                width,
+                height,
+                max_size,
+            });
+        }
+
+        let input = InputItem::new((width, height));
+        sprites_by_id.push((input.id(), name, image));
+        inputs.push(input);
+    }
+
+    let packer = SimplePacker::new().max_size(max_size).padding(1);
+    let output = packer.pack(inputs);
+
+    let sheets = output
+        .buckets()
+        .iter()
+        .map(|bucket| {
+            let (width, height) = bucket.size();
+            let mut image = DynamicImage::new_rgba8(width, height);
+            let mut slices = BTreeMap::new();
+
+            for item in bucket.items() {
+                let (_, name, sprite) = sprites_by_id
+                    .iter()
+                    .find(|(id, _, _)| *id == item.id())
+                    .expect("packer returned an unknown item");
+                let (x, y) = item.position();
+
+                imageops::overlay(&mut image, *sprite, x, y);
+                slices.insert(
+                    (*name).clone(),
+                    ImageSlice::new(item.position(), item.max()),
+                );
+            }
+
+            Spritesheet { image, slices }
+        })
+        .collect();
+
+    Ok(sheets)
+}
+
+#[derive(Debug, Error)]
+pub enum PackError {
+    #[error(
+        "Sprite {name} is {width}x{height}, which doesn't fit in a spritesheet of {}x{}",
+        .max_size.0,
+        .max_size.1
+    )]
+    TooLarge {
+        name: String,
+        width: u32,
+        height: u32,
+        max_size: (u32, u32),
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sprites(sizes: &[(u32, u32)]) -> BTreeMap<String, DynamicImage> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(index, &(width, height))| {
+                (
+                    format!("sprite-{}", index),
+                    DynamicImage::new_rgba8(width, height),
+                )
+            })
+            .collect()
+    }
+
+    fn overlaps(a: &ImageSlice, b: &ImageSlice) -> bool {
+        let (a_min, a_max) = (a.min(), a.max());
+        let (b_min, b_max) = (b.min(), b.max());
+
+        a_min.0 < b_max.0 && b_min.0 < a_max.0 && a_min.1 < b_max.1 && b_min.1 < a_max.1
+    }
+
+    #[test]
+    fn small_sprites_share_a_sheet() {
+        let sheets = pack_spritesheets(&sprites(&[(32, 32); 20]), (1024, 1024)).unwrap();
+
+        assert_eq!(sheets.len(), 1);
+        assert_eq!(sheets[0].slices.len(), 20);
+
+        let slices: Vec<_> = sheets[0].slices.values().collect();
+        for (index, a) in slices.iter().enumerate() {
+            assert_eq!(a.size(), (32, 32));
+
+            for b in &slices[index + 1..] {
+                assert!(!overlaps(a, b));
+            }
+        }
+    }
+
+    #[test]
+    fn overflow_into_more_sheets() {
+        let sheets = pack_spritesheets(&sprites(&[(100, 100); 3]), (128, 128)).unwrap();
+
+        assert_eq!(sheets.len(), 3);
+
+        for sheet in &sheets {
+            let (width, height) = sheet.image.dimensions();
+            assert!(width <= 128 && height <= 128);
+        }
+    }
+
+    #[test]
+    fn sprite_too_large() {
+        let result = pack_spritesheets(&sprites(&[(2048, 16)]), (1024, 1024));
+
+        assert!(matches!(result, Err(PackError::TooLarge { .. })));
+    }
+}
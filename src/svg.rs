@@ -0,0 +1,87 @@
+//! Rasterizes SVG images so that they can be processed and uploaded like any
+//! other image.
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use resvg::{
+    tiny_skia::{Pixmap, Transform},
+    usvg::{self, Options, Tree},
+};
+use thiserror::Error;
+
+/// Rasterizes an SVG document. If `size` is given, the image is scaled so that
+/// its largest side is `size` pixels long. Otherwise, the document's intrinsic
+/// size is used.
+pub fn rasterize_svg(data: &[u8], size: Option<u32>) -> Result<DynamicImage, SvgError> {
+    let tree = Tree::from_data(data, &Options::default())?;
+    let intrinsic_size = tree.size();
+
+    let scale = match size {
+        Some(size) => size as f32 / intrinsic_size.width().max(intrinsic_size.height()),
+        None => 1.0,
+    };
+
+    let width = (intrinsic_size.width() * scale).ceil() as u32;
+    let height = (intrinsic_size.height() * scale).ceil() as u32;
+
+    let mut pixmap = Pixmap::new(width, height).ok_or(SvgError::InvalidSize { width, height })?;
+    resvg::render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // tiny-skia stores colors with premultiplied alpha, but the image crate
+    // expects straight alpha.
+    let mut img = RgbaImage::new(width, height);
+    for (pixel, source) in img.pixels_mut().zip(pixmap.pixels()) {
+        let color = source.demultiply();
+        *pixel = Rgba([color.red(), color.green(), color.blue(), color.alpha()]);
+    }
+
+    Ok(DynamicImage::ImageRgba8(img))
+}
+
+#[derive(Debug, Error)]
+pub enum SvgError {
+    #[error("Couldn't parse SVG")]
+    Parse {
+        #[from]
+        source: usvg::Error,
+    },
+
+    #[error("Couldn't rasterize SVG to a {width}x{height} image")]
+    InvalidSize { width: u32, height: u32 },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use image::GenericImageView;
+
+    const RED_RECT: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+        <rect width="4" height="2" fill="red"/>
+    </svg>"#;
+
+    #[test]
+    fn intrinsic_size() {
+        let img = rasterize_svg(RED_RECT.as_bytes(), None).unwrap();
+
+        assert_eq!(img.dimensions(), (4, 2));
+        assert_eq!(img.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn scaled_size() {
+        let img = rasterize_svg(RED_RECT.as_bytes(), Some(16)).unwrap();
+
+        assert_eq!(img.dimensions(), (16, 8));
+    }
+
+    #[test]
+    fn invalid_svg() {
+        let result = rasterize_svg(b"not an svg", None);
+
+        assert!(matches!(result, Err(SvgError::Parse { .. })));
+    }
+}
@@ -7,11 +7,11 @@ use std::{
 };
 
 use fs_err as fs;
-use reqwest::StatusCode;
 use roblox_install::RobloxStudio;
 use thiserror::Error;
 
 use crate::data::AssetId;
+use crate::options::{ImageAssetType, ModerationFallback};
 use crate::roblox_web_api::{ImageUploadData, RobloxApiClient, RobloxApiError};
 
 pub trait SyncBackend {
@@ -27,12 +27,13 @@ pub struct UploadResponse {
 pub struct UploadInfo {
     pub name: String,
     pub contents: Vec<u8>,
-    pub hash: String,
 }
 
 pub struct RobloxSyncBackend<'a> {
     api_client: &'a mut RobloxApiClient,
     upload_to_group_id: Option<u64>,
+    max_retries_on_moderation: u32,
+    moderation_fallbacks: Vec<ModerationFallback>,
 }
 
 impl<'a> RobloxSyncBackend<'a> {
@@ -40,22 +41,39 @@ impl<'a> RobloxSyncBackend<'a> {
         Self {
             api_client,
             upload_to_group_id,
+            max_retries_on_moderation: 1,
+            moderation_fallbacks: vec![ModerationFallback::Name],
         }
     }
+
+    /// Sets how many times, and with what replaced, images are uploaded again
+    /// after Roblox moderates them.
+    pub fn moderation_retry(
+        mut self,
+        max_retries: u32,
+        fallbacks: Vec<ModerationFallback>,
+    ) -> Self {
+        self.max_retries_on_moderation = max_retries;
+        self.moderation_fallbacks = fallbacks;
+        self
+    }
 }
 
 impl<'a> SyncBackend for RobloxSyncBackend<'a> {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
         log::info!("Uploading {} to Roblox", &data.name);
 
-        let result = self
-            .api_client
-            .upload_image_with_moderation_retry(ImageUploadData {
+        let result = self.api_client.upload_image_with_moderation_retry(
+            ImageUploadData {
                 image_data: Cow::Owned(data.contents),
                 name: &data.name,
                 description: "Uploaded by Tarmac.",
                 group_id: self.upload_to_group_id,
-            });
+                asset_type: ImageAssetType::default(),
+            },
+            self.max_retries_on_moderation,
+            &self.moderation_fallbacks,
+        );
 
         match result {
             Ok(response) => {
@@ -70,10 +88,7 @@ impl<'a> SyncBackend for RobloxSyncBackend<'a> {
                 })
             }
 
-            Err(RobloxApiError::ResponseError {
-                status: StatusCode::TOO_MANY_REQUESTS,
-                ..
-            }) => Err(Error::RateLimited),
+            Err(RobloxApiError::RateLimited { .. }) => Err(Error::RateLimited),
 
             Err(err) => Err(err.into()),
         }
@@ -189,24 +204,26 @@ impl<InnerSyncBackend> RetryBackend<InnerSyncBackend> {
 
 impl<InnerSyncBackend: SyncBackend> SyncBackend for RetryBackend<InnerSyncBackend> {
     fn upload(&mut self, data: UploadInfo) -> Result<UploadResponse, Error> {
-        for index in 0..self.attempts {
-            if index != 0 {
-                log::info!(
-                    "tarmac is being rate limited, retrying upload ({}/{})",
-                    index,
-                    self.attempts - 1
-                );
-                thread::sleep(self.delay);
-            }
+        let mut index = 0;
+
+        loop {
             let result = self.inner.upload(data.clone());
 
-            match result {
-                Err(Error::RateLimited) => {}
+            // Other transient errors have already been retried by the API
+            // client under its retry policy.
+            match &result {
+                Err(Error::RateLimited) if index + 1 < self.attempts => {
+                    index += 1;
+                    log::info!(
+                        "tarmac is being rate limited, retrying upload ({}/{})",
+                        index,
+                        self.attempts - 1
+                    );
+                    thread::sleep(self.delay);
+                }
                 _ => return result,
             }
         }
-
-        Err(Error::RateLimited)
     }
 }
 
@@ -231,7 +248,7 @@ pub enum Error {
     },
 
     #[error(transparent)]
-    RobloxError {
+    RobloxApi {
         #[from]
         source: RobloxApiError,
     },
@@ -276,7 +293,6 @@ mod test {
             UploadInfo {
                 name: "foo".to_owned(),
                 contents: Vec::new(),
-                hash: "hash".to_owned(),
             }
         }
 
@@ -329,6 +345,26 @@ mod test {
             assert_eq!(upload_result, success);
         }
 
+        #[test]
+        fn server_errors_are_not_retried_again() {
+            // The API client has already retried this under its retry policy.
+            let mut counter = 0;
+            let server_error = RobloxApiError::ResponseError {
+                status: reqwest::StatusCode::BAD_GATEWAY,
+                code: None,
+                message: None,
+                body: String::new(),
+            };
+            let inner = CountUploads::new(&mut counter)
+                .with_results(vec![Err(server_error.into()), Err(Error::RateLimited)]);
+            let mut backend = RetryBackend::new(inner, 5, retry_duration());
+
+            let upload_result = backend.upload(any_upload_info()).unwrap_err();
+
+            assert_eq!(counter, 1);
+            assert!(matches!(upload_result, Error::RobloxApi { .. }));
+        }
+
         #[test]
         fn upload_returns_rate_limited_when_retries_exhausted() {
             let mut counter = 0;
@@ -343,10 +379,7 @@ mod test {
             let upload_result = backend.upload(any_upload_info()).unwrap_err();
 
             assert_eq!(counter, 3);
-            assert!(match upload_result {
-                Error::RateLimited => true,
-                _ => false,
-            });
+            assert!(matches!(upload_result, Error::RateLimited));
         }
     }
 }
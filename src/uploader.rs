@@ -0,0 +1,137 @@
+//! The entry point for using Tarmac as a library.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use structopt::StructOpt;
+
+use crate::{
+    commands::{asset_name_for, upload_image_file, UploadImageError, UploadOutcome},
+    data::UploadLock,
+    options::{GlobalOptions, ImageUploadSettings},
+    roblox_web_api::RobloxApiClient,
+};
+
+/// Configures a `Tarmac`. The defaults are the same as the defaults of the
+/// command line.
+#[derive(Debug)]
+pub struct TarmacOptions {
+    /// Options for talking to Roblox, like `--auth` and `--retries`.
+    pub global: GlobalOptions,
+
+    /// How images are processed and uploaded, like `upload-image`'s options.
+    pub settings: ImageUploadSettings,
+
+    /// The folder containing the `tarmac-lock.toml` that uploads are
+    /// recorded in.
+    pub lock_folder: PathBuf,
+}
+
+impl Default for TarmacOptions {
+    fn default() -> Self {
+        Self {
+            global: GlobalOptions::from_iter(&["tarmac"]),
+            settings: ImageUploadSettings::from_iter(&["tarmac"]),
+            lock_folder: PathBuf::from("."),
+        }
+    }
+}
+
+/// What happened to a file given to `Tarmac::upload_file`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadResult {
+    pub asset_name: String,
+    pub outcome: UploadOutcome,
+}
+
+impl UploadResult {
+    /// The ID of the image asset, unless this was a dry run.
+    pub fn asset_id(&self) -> Option<u64> {
+        self.outcome.id()
+    }
+}
+
+/// Uploads images like `tarmac upload-image`, reusing one connection to
+/// Roblox and recording every upload in `tarmac-lock.toml`. Nothing is
+/// printed; everything that happens is returned or logged.
+pub struct Tarmac {
+    client: RobloxApiClient,
+    lock: Mutex<UploadLock>,
+    settings: ImageUploadSettings,
+    lock_folder: PathBuf,
+    dry_run: bool,
+}
+
+impl Tarmac {
+    pub fn new(options: TarmacOptions) -> Result<Self, UploadImageError> {
+        let client = RobloxApiClient::from_options(&options.global)?;
+
+        client.require_auth()?;
+
+        let lock = UploadLock::read_from_folder(&options.lock_folder)?;
+
+        Ok(Self {
+            client,
+            lock: Mutex::new(lock),
+            settings: options.settings,
+            lock_folder: options.lock_folder,
+            dry_run: options.global.dry_run,
+        })
+    }
+
+    /// Uploads an image, naming it after its file.
+    pub fn upload_file(&mut self, path: &Path) -> Result<UploadResult, UploadImageError> {
+        self.upload_file_named(path, &asset_name_for(path))
+    }
+
+    /// Uploads an image with the given name. Images that haven't changed since
+    /// they were last uploaded aren't uploaded again unless `force` is set.
+    pub fn upload_file_named(
+        &mut self,
+        path: &Path,
+        name: &str,
+    ) -> Result<UploadResult, UploadImageError> {
+        let outcome = upload_image_file(
+            &mut self.client,
+            &self.lock,
+            path,
+            name,
+            &self.settings,
+            self.dry_run,
+        )?;
+
+        if let UploadOutcome::Uploaded(_) = outcome {
+            self.lock
+                .get_mut()
+                .unwrap()
+                .write_to_folder(&self.lock_folder)?;
+        }
+
+        Ok(UploadResult {
+            asset_name: name.to_owned(),
+            outcome,
+        })
+    }
+
+    /// The uploads recorded so far, including ones from earlier runs.
+    pub fn lock(&mut self) -> &UploadLock {
+        self.lock.get_mut().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_options_match_command_line() {
+        let options = TarmacOptions::default();
+
+        assert_eq!(options.settings.max_size, 1024);
+        assert_eq!(options.settings.description, "Uploaded by Tarmac.");
+        assert!(!options.global.dry_run);
+        assert_eq!(options.lock_folder, Path::new("."));
+    }
+}